@@ -11,7 +11,10 @@ pub mod data {
     tonic::include_proto!("data");
 }
 use crate::analytics::{
-    GetMacdRequest, GetMovingAverageRequest, GetTradeAnalyticsRequest, SubscribeToTradesRequest,
+    ComputeIndicatorRequest, ExecuteQueryRequest, GetAtrRequest, GetBandsResponse,
+    GetBollingerBandsRequest, GetCandlesRequest, GetKeltnerChannelsRequest, GetMacdRequest,
+    GetMovingAverageRequest, GetTradeAnalyticsRequest, ListIndicatorsRequest, SeriesType,
+    SubscribeToIndicatorRequest, SubscribeToTradesRequest,
 };
 use analytics::analytics_service_client::AnalyticsServiceClient;
 
@@ -41,6 +44,74 @@ enum Commands {
         end_timestamp: u64,
         #[arg(short, long, default_value_t = 20)]
         window_size: u32,
+        /// Candle width in seconds; runs over individual trades when omitted.
+        #[arg(short, long)]
+        interval: Option<u64>,
+        /// Candle field to average: close, open, high or low.
+        #[arg(long, default_value = "close", value_parser = parse_series_type)]
+        series_type: SeriesType,
+    },
+    Rsi {
+        #[arg(short, long)]
+        symbol: String,
+        #[arg(long)]
+        start_timestamp: u64,
+        #[arg(long)]
+        end_timestamp: u64,
+        #[arg(short, long, default_value_t = 14)]
+        window_size: u32,
+        /// Candle width in seconds; runs over individual trades when omitted.
+        #[arg(short, long)]
+        interval: Option<u64>,
+        #[arg(long, default_value = "close", value_parser = parse_series_type)]
+        series_type: SeriesType,
+    },
+    Bollinger {
+        #[arg(short, long)]
+        symbol: String,
+        #[arg(long)]
+        start_timestamp: u64,
+        #[arg(long)]
+        end_timestamp: u64,
+        /// Candle width in seconds.
+        #[arg(short, long, default_value_t = 60)]
+        interval: u64,
+        #[arg(short, long, default_value_t = 20)]
+        window_size: u32,
+        #[arg(long, default_value_t = 2.0)]
+        num_std_dev: f64,
+        #[arg(long, default_value = "close", value_parser = parse_series_type)]
+        series_type: SeriesType,
+    },
+    Atr {
+        #[arg(short, long)]
+        symbol: String,
+        #[arg(long)]
+        start_timestamp: u64,
+        #[arg(long)]
+        end_timestamp: u64,
+        /// Candle width in seconds.
+        #[arg(short, long, default_value_t = 60)]
+        interval: u64,
+        #[arg(short, long, default_value_t = 14)]
+        window_size: u32,
+    },
+    Keltner {
+        #[arg(short, long)]
+        symbol: String,
+        #[arg(long)]
+        start_timestamp: u64,
+        #[arg(long)]
+        end_timestamp: u64,
+        /// Candle width in seconds.
+        #[arg(short, long, default_value_t = 60)]
+        interval: u64,
+        #[arg(short, long, default_value_t = 20)]
+        window_size: u32,
+        #[arg(long, default_value_t = 10)]
+        atr_window_size: u32,
+        #[arg(short, long, default_value_t = 2.0)]
+        multiplier: f64,
     },
     Subscribe {
         #[arg(short, long)]
@@ -59,9 +130,116 @@ enum Commands {
         slow_period: u32,
         #[arg(long, default_value_t = 9)]
         signal_period: u32,
+        /// Candle width in seconds; runs over individual trades when omitted.
+        #[arg(short, long)]
+        interval: Option<u64>,
+        #[arg(long, default_value = "close", value_parser = parse_series_type)]
+        series_type: SeriesType,
+    },
+    Candles {
+        #[arg(short, long)]
+        symbol: String,
+        #[arg(long)]
+        start_timestamp: u64,
+        #[arg(long)]
+        end_timestamp: u64,
+        /// Bucket width in seconds.
+        #[arg(short, long, default_value_t = 60)]
+        interval: u64,
+    },
+    /// Lists the indicators the server can compute.
+    Indicators,
+    /// Computes any indicator listed by `indicators`.
+    Indicator {
+        name: String,
+        #[arg(short, long)]
+        symbol: String,
+        #[arg(long)]
+        start_timestamp: u64,
+        #[arg(long)]
+        end_timestamp: u64,
+        /// Parameter as `name=value`; may be repeated.
+        #[arg(short, long = "param", value_parser = parse_param)]
+        params: Vec<(String, f64)>,
+        /// Candle width in seconds; runs over individual trades when omitted.
+        #[arg(short, long)]
+        interval: Option<u64>,
+        #[arg(long, default_value = "close", value_parser = parse_series_type)]
+        series_type: SeriesType,
+    },
+    /// Streams live values of an indicator as candles close.
+    Watch {
+        name: String,
+        #[arg(short, long)]
+        symbol: String,
+        /// Parameter as `name=value`; may be repeated.
+        #[arg(short, long = "param", value_parser = parse_param)]
+        params: Vec<(String, f64)>,
+        /// Candle width in seconds.
+        #[arg(short, long, default_value_t = 60)]
+        interval: u64,
+        #[arg(long, default_value = "close", value_parser = parse_series_type)]
+        series_type: SeriesType,
+        /// Also print a provisional value after every trade.
+        #[arg(long)]
+        provisional: bool,
+    },
+    /// Runs read-only SQL over the `trades` and `candles` tables and saves the
+    /// result as an Arrow IPC stream.
+    Query {
+        sql: String,
+        /// File the Arrow IPC stream is written to.
+        #[arg(short, long, default_value = "query.arrows")]
+        output: std::path::PathBuf,
+        /// Rows to return at most; the server's limit applies when omitted.
+        #[arg(long, default_value_t = 0)]
+        max_rows: u64,
     },
 }
 
+fn parse_series_type(value: &str) -> Result<SeriesType, String> {
+    SeriesType::from_str_name(&format!("SERIES_TYPE_{}", value.to_uppercase()))
+        .ok_or_else(|| format!("unknown series type `{value}`"))
+}
+
+fn parse_param(value: &str) -> Result<(String, f64), String> {
+    let (name, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected `name=value`, got `{value}`"))?;
+    let value = value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number"))?;
+    Ok((name.to_string(), value))
+}
+
+fn interval(seconds: Option<u64>) -> Option<prost_types::Duration> {
+    seconds.map(|seconds| prost_types::Duration {
+        seconds: seconds as i64,
+        nanos: 0,
+    })
+}
+
+fn print_bands(name: &str, data: &GetBandsResponse) {
+    println!("\n✅ {} Complete! (showing last 10 points)", name);
+    println!("------------------------------------");
+    println!(
+        "{:<28} | {:<15} | {:<15} | {:<15}",
+        "Timestamp", "Upper", "Middle", "Lower"
+    );
+    println!("------------------------------------");
+    for point in data.points.iter().rev().take(10).rev() {
+        println!(
+            "{:<28} | {:<15.2} | {:<15.2} | {:<15.2}",
+            format_timestamp_us(point.timestamp),
+            point.upper,
+            point.middle,
+            point.lower
+        );
+    }
+    println!("------------------------------------");
+    println!("Total points calculated: {}", data.points.len());
+}
+
 fn format_timestamp_us(us: u64) -> String {
     let seconds = (us / 1_000_000) as i64;
     let nanoseconds = (us % 1_000_000 * 1000) as u32;
@@ -118,6 +296,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             start_timestamp,
             end_timestamp,
             window_size,
+            interval: interval_seconds,
+            series_type,
         } => {
             let request = tonic::Request::new(GetMovingAverageRequest {
                 symbol: symbol.clone(),
@@ -130,6 +310,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     nanos: 0,
                 }),
                 window_size: window_size as u32,
+                interval: interval(interval_seconds),
+                series_type: series_type.into(),
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
             });
             let response = client.get_moving_average(request).await?;
             let data = response.into_inner();
@@ -148,6 +333,148 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("Total points calculated: {}", data.points.len());
         }
 
+        Commands::Rsi {
+            symbol,
+            start_timestamp,
+            end_timestamp,
+            window_size,
+            interval: interval_seconds,
+            series_type,
+        } => {
+            let request = tonic::Request::new(GetMovingAverageRequest {
+                symbol: symbol.clone(),
+                start_timestamp: Some(Timestamp {
+                    seconds: start_timestamp as i64,
+                    nanos: 0,
+                }),
+                end_timestamp: Some(Timestamp {
+                    seconds: end_timestamp as i64,
+                    nanos: 0,
+                }),
+                window_size,
+                interval: interval(interval_seconds),
+                series_type: series_type.into(),
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
+            });
+            let response = client.get_rsi(request).await?;
+            let data = response.into_inner();
+            println!("\n✅ RSI Analysis Complete! (showing last 10 points)");
+            println!("------------------------------------");
+            println!("{:<28} | {:<15}", "Timestamp", "RSI");
+            println!("------------------------------------");
+            for point in data.points.iter().rev().take(10).rev() {
+                println!(
+                    "{:<28} | {:<15.2}",
+                    format_timestamp_us(point.timestamp),
+                    point.value
+                );
+            }
+            println!("------------------------------------");
+            println!("Total points calculated: {}", data.points.len());
+        }
+
+        Commands::Bollinger {
+            symbol,
+            start_timestamp,
+            end_timestamp,
+            interval: interval_seconds,
+            window_size,
+            num_std_dev,
+            series_type,
+        } => {
+            let request = tonic::Request::new(GetBollingerBandsRequest {
+                symbol,
+                start_timestamp: Some(Timestamp {
+                    seconds: start_timestamp as i64,
+                    nanos: 0,
+                }),
+                end_timestamp: Some(Timestamp {
+                    seconds: end_timestamp as i64,
+                    nanos: 0,
+                }),
+                interval: interval(Some(interval_seconds)),
+                window_size,
+                num_std_dev,
+                series_type: series_type.into(),
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
+            });
+            let response = client.get_bollinger_bands(request).await?;
+            print_bands("Bollinger Bands", &response.into_inner());
+        }
+        Commands::Atr {
+            symbol,
+            start_timestamp,
+            end_timestamp,
+            interval: interval_seconds,
+            window_size,
+        } => {
+            let request = tonic::Request::new(GetAtrRequest {
+                symbol,
+                start_timestamp: Some(Timestamp {
+                    seconds: start_timestamp as i64,
+                    nanos: 0,
+                }),
+                end_timestamp: Some(Timestamp {
+                    seconds: end_timestamp as i64,
+                    nanos: 0,
+                }),
+                interval: interval(Some(interval_seconds)),
+                window_size,
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
+            });
+            let response = client.get_atr(request).await?;
+            let data = response.into_inner();
+            println!("\n✅ ATR Analysis Complete! (showing last 10 points)");
+            println!("------------------------------------");
+            println!("{:<28} | {:<15}", "Timestamp", "ATR");
+            println!("------------------------------------");
+            for point in data.points.iter().rev().take(10).rev() {
+                println!(
+                    "{:<28} | {:<15.4}",
+                    format_timestamp_us(point.timestamp),
+                    point.value
+                );
+            }
+            println!("------------------------------------");
+            println!("Total points calculated: {}", data.points.len());
+        }
+        Commands::Keltner {
+            symbol,
+            start_timestamp,
+            end_timestamp,
+            interval: interval_seconds,
+            window_size,
+            atr_window_size,
+            multiplier,
+        } => {
+            let request = tonic::Request::new(GetKeltnerChannelsRequest {
+                symbol,
+                start_timestamp: Some(Timestamp {
+                    seconds: start_timestamp as i64,
+                    nanos: 0,
+                }),
+                end_timestamp: Some(Timestamp {
+                    seconds: end_timestamp as i64,
+                    nanos: 0,
+                }),
+                interval: interval(Some(interval_seconds)),
+                window_size,
+                atr_window_size,
+                multiplier,
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
+            });
+            let response = client.get_keltner_channels(request).await?;
+            print_bands("Keltner Channels", &response.into_inner());
+        }
+
         Commands::Subscribe { symbol } => {
             let request = tonic::Request::new(SubscribeToTradesRequest {
                 symbol: symbol.clone(),
@@ -167,6 +494,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             fast_period,
             slow_period,
             signal_period,
+            interval: interval_seconds,
+            series_type,
         } => {
             let request = tonic::Request::new(GetMacdRequest {
                 start_timestamp: Some(Timestamp {
@@ -181,6 +510,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 slow_period: slow_period as u32,
                 signal_period: signal_period as u32,
                 symbol: symbol.clone(),
+                interval: interval(interval_seconds),
+                series_type: series_type.into(),
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
             });
             let response = client.get_macd(request).await?;
             let data = response.into_inner();
@@ -204,6 +538,184 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("------------------------------------");
             println!("Total points calculated: {}", data.points.len());
         }
+        Commands::Candles {
+            symbol,
+            start_timestamp,
+            end_timestamp,
+            interval,
+        } => {
+            let request = tonic::Request::new(GetCandlesRequest {
+                symbol: symbol.clone(),
+                start_timestamp: Some(Timestamp {
+                    seconds: start_timestamp as i64,
+                    nanos: 0,
+                }),
+                end_timestamp: Some(Timestamp {
+                    seconds: end_timestamp as i64,
+                    nanos: 0,
+                }),
+                interval: Some(prost_types::Duration {
+                    seconds: interval as i64,
+                    nanos: 0,
+                }),
+            });
+            let response = client.get_candles(request).await?;
+            let data = response.into_inner();
+            println!("\n✅ Candles Complete!");
+            println!("------------------------------------");
+            println!(
+                "{:<28} | {:<12} | {:<12} | {:<12} | {:<12} | {:<12} | {:<12} | {:<12} | {:<8}",
+                "Timestamp",
+                "Open",
+                "High",
+                "Low",
+                "Close",
+                "Volume",
+                "Buy Vol",
+                "Sell Vol",
+                "Trades"
+            );
+            println!("------------------------------------");
+            for candle in &data.candles {
+                println!(
+                    "{:<28} | {:<12.2} | {:<12.2} | {:<12.2} | {:<12.2} | {:<12.4} | {:<12.4} | {:<12.4} | {:<8}",
+                    format_timestamp_us(candle.timestamp),
+                    candle.open,
+                    candle.high,
+                    candle.low,
+                    candle.close,
+                    candle.volume,
+                    candle.buy_volume,
+                    candle.sell_volume,
+                    candle.trades_count
+                );
+            }
+            println!("------------------------------------");
+            println!("Total candles: {}", data.candles.len());
+        }
+        Commands::Indicators => {
+            let response = client.list_indicators(ListIndicatorsRequest {}).await?;
+            for indicator in response.into_inner().indicators {
+                println!(
+                    "{:<12} {} -> [{}]",
+                    indicator.name,
+                    indicator.description,
+                    indicator.outputs.join(", ")
+                );
+                for parameter in indicator.parameters {
+                    println!(
+                        "    {:<16} {} (default {})",
+                        parameter.name, parameter.description, parameter.default_value
+                    );
+                }
+            }
+        }
+        Commands::Indicator {
+            name,
+            symbol,
+            start_timestamp,
+            end_timestamp,
+            params,
+            interval: interval_seconds,
+            series_type,
+        } => {
+            let request = tonic::Request::new(ComputeIndicatorRequest {
+                name: name.clone(),
+                params: params.into_iter().collect(),
+                symbol,
+                start_timestamp: Some(Timestamp {
+                    seconds: start_timestamp as i64,
+                    nanos: 0,
+                }),
+                end_timestamp: Some(Timestamp {
+                    seconds: end_timestamp as i64,
+                    nanos: 0,
+                }),
+                interval: interval(interval_seconds),
+                series_type: series_type.into(),
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
+            });
+            let data = client.compute_indicator(request).await?.into_inner();
+            println!(
+                "\n✅ {} Complete! (showing last 10 points)",
+                name.to_uppercase()
+            );
+            println!("------------------------------------");
+            println!("{:<28} | {}", "Timestamp", data.outputs.join(" | "));
+            println!("------------------------------------");
+            for point in data.points.iter().rev().take(10).rev() {
+                let values: Vec<String> = point.values.iter().map(|v| format!("{v:.2}")).collect();
+                println!(
+                    "{:<28} | {}",
+                    format_timestamp_us(point.timestamp),
+                    values.join(" | ")
+                );
+            }
+            println!("------------------------------------");
+            println!("Total points calculated: {}", data.points.len());
+        }
+        Commands::Watch {
+            name,
+            symbol,
+            params,
+            interval: interval_seconds,
+            series_type,
+            provisional,
+        } => {
+            let request = tonic::Request::new(SubscribeToIndicatorRequest {
+                symbol: symbol.clone(),
+                name: name.clone(),
+                params: params.into_iter().collect(),
+                interval: interval(Some(interval_seconds)),
+                series_type: series_type.into(),
+                start_timestamp: None,
+                provisional,
+            });
+            println!(
+                "Watching {} for symbol '{}'...",
+                name.to_uppercase(),
+                symbol
+            );
+            let mut updates = client.subscribe_to_indicator(request).await?.into_inner();
+            while let Some(update) = updates.message().await? {
+                let values: Vec<String> = update.values.iter().map(|v| format!("{v:.2}")).collect();
+                println!(
+                    "{:<28} | {}{}",
+                    format_timestamp_us(update.timestamp),
+                    values.join(" | "),
+                    if update.provisional {
+                        " (provisional)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+            println!("Stream closed!");
+        }
+        Commands::Query {
+            sql,
+            output,
+            max_rows,
+        } => {
+            let response = client
+                .max_decoding_message_size(usize::MAX)
+                .execute_query(ExecuteQueryRequest { sql, max_rows })
+                .await?
+                .into_inner();
+            std::fs::write(&output, &response.arrow_ipc)?;
+            println!(
+                "Wrote {} rows to {}{}",
+                response.rows,
+                output.display(),
+                if response.truncated {
+                    " (truncated)"
+                } else {
+                    ""
+                }
+            );
+        }
     }
     Ok(())
 }
@@ -1,28 +1,97 @@
 use crate::types::*;
 use yew::prelude::*;
 
+const CHART_WIDTH: f64 = 800.0;
+const CHART_HEIGHT: f64 = 256.0;
+
 #[derive(Properties, PartialEq)]
 pub struct ChartProps {
-    pub data: Vec<MovingAveragePoint>,
+    pub candles: Vec<Candle>,
+    /// Candle width in microseconds.
+    pub interval: u64,
+    /// Drawn as a line over the candles, e.g. a moving average.
+    #[prop_or_default]
+    pub overlay: Vec<MovingAveragePoint>,
     pub title: String,
 }
 
 #[function_component(Chart)]
 pub fn chart(props: &ChartProps) -> Html {
+    let (Some(first), Some(last)) = (props.candles.first(), props.candles.last()) else {
+        return html! {
+            <div class="bg-white shadow rounded-lg p-6">
+                <h3 class="text-lg font-medium text-gray-900 mb-4">{&props.title}</h3>
+                <div class="text-sm text-gray-600">{"No candles in range"}</div>
+            </div>
+        };
+    };
+
+    let start = first.timestamp as f64;
+    let span = (last.timestamp + props.interval) as f64 - start;
+    let x = |timestamp: u64| (timestamp as f64 - start) / span * CHART_WIDTH;
+
+    let prices = props
+        .candles
+        .iter()
+        .flat_map(|c| [c.low, c.high])
+        .chain(props.overlay.iter().map(|p| p.value));
+    let low = prices.clone().fold(f64::INFINITY, f64::min);
+    let high = prices.fold(f64::NEG_INFINITY, f64::max);
+    let range = if high > low { high - low } else { 1.0 };
+    let y = |price: f64| (high - price) / range * CHART_HEIGHT;
+
+    let slot = props.interval as f64 / span * CHART_WIDTH;
+    let body_width = (slot * 0.7).max(1.0);
+    let overlay = props
+        .overlay
+        .iter()
+        .map(|p| format!("{:.2},{:.2}", x(p.timestamp), y(p.value)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
     html! {
         <div class="bg-white shadow rounded-lg p-6">
             <h3 class="text-lg font-medium text-gray-900 mb-4">{&props.title}</h3>
-            <div class="h-64 flex items-end justify-between space-x-1">
-                {props.data.iter().enumerate().map(|(i, point)| {
-                    let height = (point.value / props.data.iter().map(|p| p.value).fold(0.0, f64::max) * 200.0) as u32;
+            <svg
+                class="w-full h-64"
+                viewBox={format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT)}
+                preserveAspectRatio="none"
+            >
+                {props.candles.iter().enumerate().map(|(i, candle)| {
+                    let centre = x(candle.timestamp) + slot / 2.0;
+                    let color = if candle.close >= candle.open { "#22c55e" } else { "#ef4444" };
+                    let body_top = y(candle.open.max(candle.close));
+                    let body_height = (y(candle.open.min(candle.close)) - body_top).max(1.0);
                     html! {
-                        <div key={i} class="bg-blue-500 rounded-t" style={format!("height: {}px; width: 4px;", height)}>
-                        </div>
+                        <g key={i}>
+                            <line
+                                x1={centre.to_string()} x2={centre.to_string()}
+                                y1={y(candle.high).to_string()} y2={y(candle.low).to_string()}
+                                stroke={color}
+                            />
+                            <rect
+                                x={(centre - body_width / 2.0).to_string()}
+                                y={body_top.to_string()}
+                                width={body_width.to_string()}
+                                height={body_height.to_string()}
+                                fill={color}
+                            />
+                        </g>
                     }
-                }).collect::<Vec<_>>()}
-            </div>
-            <div class="mt-4 text-sm text-gray-600">
-                {"Latest: "}{format!("{:.2}", props.data.last().map(|p| p.value).unwrap_or(0.0))}
+                }).collect::<Html>()}
+                if !props.overlay.is_empty() {
+                    <polyline points={overlay} fill="none" stroke="#3b82f6" stroke-width="2" />
+                }
+            </svg>
+            <div class="mt-4 text-sm text-gray-600 space-y-1">
+                <div>
+                    {"Last close: "}{format!("{:.2}", last.close)}
+                    {" · VWAP: "}{format!("{:.2}", last.vwap)}
+                    {" · Buy/Sell volume: "}{format!("{:.4} / {:.4}", last.buy_volume, last.sell_volume)}
+                </div>
+                if let Some(point) = props.overlay.last() {
+                    <div>{"Latest: "}{format!("{:.2}", point.value)}</div>
+                }
             </div>
         </div>
     }
@@ -16,6 +16,8 @@ pub struct AnalyticsRequest {
     pub fast_period: u32,
     pub slow_period: u32,
     pub signal_period: u32,
+    /// Candle width.
+    pub interval_seconds: u32,
 }
 
 #[function_component(AnalyticsForm)]
@@ -26,6 +28,7 @@ pub fn analytics_form(props: &AnalyticsFormProps) -> Html {
     let fast_period = use_state(|| 12u32);
     let slow_period = use_state(|| 26u32);
     let signal_period = use_state(|| 9u32);
+    let interval_seconds = use_state(|| 5u32);
 
     let on_submit = {
         let symbol = symbol.clone();
@@ -34,6 +37,7 @@ pub fn analytics_form(props: &AnalyticsFormProps) -> Html {
         let fast_period = fast_period.clone();
         let slow_period = slow_period.clone();
         let signal_period = signal_period.clone();
+        let interval_seconds = interval_seconds.clone();
         let on_submit = props.on_submit.clone();
 
         Callback::from(move |e: SubmitEvent| {
@@ -51,6 +55,7 @@ pub fn analytics_form(props: &AnalyticsFormProps) -> Html {
                 fast_period: *fast_period,
                 slow_period: *slow_period,
                 signal_period: *signal_period,
+                interval_seconds: *interval_seconds,
             };
             on_submit.emit(request);
         })
@@ -87,6 +92,7 @@ pub fn analytics_form(props: &AnalyticsFormProps) -> Html {
                                 let new_type = match select.get_attribute("value").unwrap_or_default().as_str() {
                                     "SMA" => AnalyticsType::Sma,
                                     "MACD" => AnalyticsType::Macd,
+                                    "CANDLES" => AnalyticsType::Candles,
                                     _ => AnalyticsType::Vwap,
                                 };
                                 analytics_type.set(new_type);
@@ -97,12 +103,33 @@ pub fn analytics_form(props: &AnalyticsFormProps) -> Html {
                         <option value="VWAP">{"VWAP"}</option>
                         <option value="SMA">{"SMA"}</option>
                         <option value="MACD">{"MACD"}</option>
+                        <option value="CANDLES">{"Candles"}</option>
                     </select>
                 </div>
 
+                if matches!(analytics_type_value, AnalyticsType::Sma | AnalyticsType::Candles) {
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700">{"Candle Interval (seconds)"}</label>
+                        <input
+                            type="number"
+                            value={(*interval_seconds).to_string()}
+                            oninput={Callback::from(move |e: InputEvent| {
+                                if let Some(input) = e.target_dyn_into::<web_sys::HtmlInputElement>() {
+                                    if let Ok(value) = input.value().parse::<u32>() {
+                                        interval_seconds.set(value);
+                                    }
+                                }
+                            })}
+                            class="mt-1 block w-full border-gray-300 rounded-md shadow-sm focus:ring-blue-500 focus:border-blue-500"
+                            min="1"
+                            max="300"
+                        />
+                    </div>
+                }
+
                 if analytics_type_value == AnalyticsType::Sma {
                     <div>
-                        <label class="block text-sm font-medium text-gray-700">{"Window Size"}</label>
+                        <label class="block text-sm font-medium text-gray-700">{"Window Size (candles)"}</label>
                         <input
                             type="number"
                             value={(*window_size).to_string()}
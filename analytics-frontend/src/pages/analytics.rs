@@ -10,6 +10,7 @@ pub fn analytics() -> Html {
     let vwap_data = use_state(|| None::<TradeAnalytics>);
     let sma_data = use_state(|| None::<Vec<MovingAveragePoint>>);
     let macd_data = use_state(|| None::<Vec<MacdPoint>>);
+    let candle_data = use_state(|| None::<(Vec<Candle>, u64)>);
 
     let on_submit = {
         let loading = loading.clone();
@@ -17,6 +18,7 @@ pub fn analytics() -> Html {
         let vwap_data = vwap_data.clone();
         let sma_data = sma_data.clone();
         let macd_data = macd_data.clone();
+        let candle_data = candle_data.clone();
 
         Callback::from(move |request: AnalyticsRequest| {
             let loading = loading.clone();
@@ -24,12 +26,14 @@ pub fn analytics() -> Html {
             let vwap_data = vwap_data.clone();
             let sma_data = sma_data.clone();
             let macd_data = macd_data.clone();
+            let candle_data = candle_data.clone();
 
             loading.set(true);
             error.set(None);
 
             wasm_bindgen_futures::spawn_local(async move {
                 log::info!("Making analytics request for: {:?}", request.analytics_type);
+                let interval_micros = request.interval_seconds as u64 * 1_000_000;
 
                 let result = match request.analytics_type {
                     AnalyticsType::Vwap => {
@@ -68,13 +72,30 @@ pub fn analytics() -> Html {
                             request.time_range.start,
                             request.time_range.end,
                             request.window_size,
+                            Some(request.interval_seconds),
                         )
                         .await
                         {
                             Ok(data) => {
                                 log::info!("SMA request successful, got {} points", data.len());
                                 sma_data.set(Some(data));
-                                Ok(())
+                                match AnalyticsService::get_candles(
+                                    &request.symbol,
+                                    request.time_range.start,
+                                    request.time_range.end,
+                                    request.interval_seconds,
+                                )
+                                .await
+                                {
+                                    Ok(candles) => {
+                                        candle_data.set(Some((candles, interval_micros)));
+                                        Ok(())
+                                    }
+                                    Err(e) => {
+                                        log::error!("Candles request failed: {}", e);
+                                        Err(e.to_string())
+                                    }
+                                }
                             }
                             Err(e) => {
                                 log::error!("SMA request failed: {}", e);
@@ -111,6 +132,35 @@ pub fn analytics() -> Html {
                             }
                         }
                     }
+                    AnalyticsType::Candles => {
+                        log::info!(
+                            "Requesting {}s candles for symbol: {}",
+                            request.interval_seconds,
+                            request.symbol
+                        );
+                        match AnalyticsService::get_candles(
+                            &request.symbol,
+                            request.time_range.start,
+                            request.time_range.end,
+                            request.interval_seconds,
+                        )
+                        .await
+                        {
+                            Ok(data) => {
+                                log::info!(
+                                    "Candles request successful, got {} candles",
+                                    data.len()
+                                );
+                                sma_data.set(None);
+                                candle_data.set(Some((data, interval_micros)));
+                                Ok(())
+                            }
+                            Err(e) => {
+                                log::error!("Candles request failed: {}", e);
+                                Err(e.to_string())
+                            }
+                        }
+                    }
                 };
 
                 loading.set(false);
@@ -170,10 +220,12 @@ pub fn analytics() -> Html {
                 </div>
             </div>
 
-            if let Some(data) = (*sma_data).as_ref() {
+            if let Some((candles, interval)) = (*candle_data).as_ref() {
                 <Chart
-                    data={data.clone()}
-                    title={"Simple Moving Average"}
+                    candles={candles.clone()}
+                    interval={*interval}
+                    overlay={(*sma_data).clone().unwrap_or_default()}
+                    title={if sma_data.is_some() { "Simple Moving Average" } else { "Candles" }}
                 />
             }
 
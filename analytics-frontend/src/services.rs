@@ -6,6 +6,8 @@ use prost::Message;
 use reqwest::Client;
 // use tonic_web_wasm_client::Client;
 
+// The frontend calls only a few of the server's RPCs.
+#[allow(dead_code)]
 mod analytics {
     include!(concat!(env!("OUT_DIR"), "/analytics.rs"));
 }
@@ -17,6 +19,8 @@ use analytics::{
     GetMacdRequest, GetMovingAverageRequest, GetTradeAnalyticsRequest, SubscribeToTradesRequest,
 };
 const ANALYTICS_SERVER_URL: &str = "http://localhost:50051";
+/// Points the chart can draw; longer series are downsampled by the server.
+const CHART_MAX_POINTS: u32 = 2000;
 
 type Error = Box<dyn std::error::Error>;
 
@@ -124,6 +128,7 @@ impl AnalyticsService {
         start_timestamp: DateTime<Utc>,
         end_timestamp: DateTime<Utc>,
         window_size: u32,
+        interval_seconds: Option<u32>,
     ) -> Result<Vec<MovingAveragePoint>, Error> {
         let url = format!(
             "{}/analytics.AnalyticsService/GetMovingAverage",
@@ -142,6 +147,14 @@ impl AnalyticsService {
                 nanos: end_timestamp.timestamp_subsec_nanos() as i32,
             }),
             window_size,
+            interval: interval_seconds.map(|seconds| prost_types::Duration {
+                seconds: seconds as i64,
+                nanos: 0,
+            }),
+            series_type: analytics::SeriesType::Close.into(),
+            page_size: 0,
+            page_token: String::new(),
+            max_points: CHART_MAX_POINTS,
         };
 
         let response = client
@@ -205,6 +218,11 @@ impl AnalyticsService {
             fast_period,
             slow_period,
             signal_period,
+            interval: None,
+            series_type: analytics::SeriesType::Close.into(),
+            page_size: 0,
+            page_token: String::new(),
+            max_points: CHART_MAX_POINTS,
         };
         let response = client
             .post(url)
@@ -240,6 +258,79 @@ impl AnalyticsService {
         Ok(points)
     }
 
+    pub async fn get_candles(
+        symbol: &str,
+        start_timestamp: DateTime<Utc>,
+        end_timestamp: DateTime<Utc>,
+        interval_seconds: u32,
+    ) -> Result<Vec<Candle>, Error> {
+        let url = format!(
+            "{}/analytics.AnalyticsService/GetCandles",
+            ANALYTICS_SERVER_URL
+        );
+        let client = Client::new();
+
+        let request_bytes = analytics::GetCandlesRequest {
+            symbol: symbol.to_string(),
+            start_timestamp: Some(prost_types::Timestamp {
+                seconds: start_timestamp.timestamp(),
+                nanos: start_timestamp.timestamp_subsec_nanos() as i32,
+            }),
+            end_timestamp: Some(prost_types::Timestamp {
+                seconds: end_timestamp.timestamp(),
+                nanos: end_timestamp.timestamp_subsec_nanos() as i32,
+            }),
+            interval: Some(prost_types::Duration {
+                seconds: interval_seconds as i64,
+                nanos: 0,
+            }),
+        }
+        .encode_to_vec();
+
+        let mut body = Vec::with_capacity(5 + request_bytes.len());
+        body.push(0);
+        body.extend_from_slice(&(request_bytes.len() as u32).to_be_bytes());
+        body.extend_from_slice(&request_bytes);
+
+        let response = client
+            .post(url)
+            .header("Content-Type", "application/grpc-web")
+            .header("Accept", "application/grpc-web")
+            .body(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Erro na requisição: {}", response.status()).into());
+        }
+
+        let response_body = response.bytes().await?;
+
+        if response_body.len() < 5 {
+            return Err("Resposta inválida do servidor".into());
+        }
+        let response_message = analytics::GetCandlesResponse::decode(&response_body[5..])?;
+
+        let candles = response_message
+            .candles
+            .into_iter()
+            .map(|candle| Candle {
+                timestamp: candle.timestamp,
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+                volume: candle.volume,
+                vwap: candle.vwap,
+                trades_count: candle.trades_count,
+                buy_volume: candle.buy_volume,
+                sell_volume: candle.sell_volume,
+            })
+            .collect();
+
+        Ok(candles)
+    }
+
     pub async fn subscribe_to_trades(
         symbol: &str,
         on_trade: impl Fn(Trade) + 'static,
@@ -23,6 +23,21 @@ pub struct MacdPoint {
     pub histogram: f64,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Start of the bucket, in microseconds since the epoch.
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub vwap: f64,
+    pub trades_count: u64,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub symbol: String,
@@ -37,6 +52,7 @@ pub enum AnalyticsType {
     Vwap,
     Sma,
     Macd,
+    Candles,
 }
 
 impl AnalyticsType {
@@ -45,6 +61,7 @@ impl AnalyticsType {
             AnalyticsType::Vwap => "VWAP",
             AnalyticsType::Sma => "SMA",
             AnalyticsType::Macd => "MACD",
+            AnalyticsType::Candles => "CANDLES",
         }
     }
 }
@@ -0,0 +1,103 @@
+//! Compares pushed-down aggregation with the in-process DataFusion fallback.
+//!
+//! Always runs over a synthetic day of trades held in memory. Set
+//! `ANALYTICS_CLICKHOUSE_URL` to also run against ClickHouse, over the last
+//! `ANALYTICS_BENCH_HOURS` (default 1) of `ANALYTICS_BENCH_SYMBOL` (default
+//! BTCUSDT) trades.
+
+use std::env;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use analytics_server::data::trade::Side;
+use analytics_server::planner::{Plan, Planner};
+use analytics_server::store::clickhouse::ClickHouseStore;
+use analytics_server::store::memory::MemoryStore;
+use analytics_server::store::{TimeRange, TradeStore};
+use clickhouse::Client;
+use criterion::{Criterion, criterion_group, criterion_main};
+use tokio::runtime::Runtime;
+
+const SYMBOL: &str = "BTCUSDT";
+const TRADES: u64 = 500_000;
+const DAY_MICROS: u64 = 86_400_000_000;
+
+fn synthetic() -> MemoryStore {
+    let mut store = MemoryStore::new();
+    for i in 0..TRADES {
+        let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+        store.insert(
+            SYMBOL,
+            i * (DAY_MICROS / TRADES),
+            30_000.0 + (i as f64 / 1_000.0).sin() * 500.0,
+            0.001 + (i % 10) as f64 * 0.01,
+            side,
+        );
+    }
+    store
+}
+
+fn targets() -> Vec<(&'static str, Arc<dyn TradeStore>, String, TimeRange)> {
+    let mut targets: Vec<(&'static str, Arc<dyn TradeStore>, String, TimeRange)> = vec![(
+        "memory",
+        Arc::new(synthetic()),
+        SYMBOL.to_string(),
+        TimeRange {
+            start_micros: 0,
+            end_micros: DAY_MICROS,
+        },
+    )];
+    if let Ok(url) = env::var("ANALYTICS_CLICKHOUSE_URL") {
+        let symbol = env::var("ANALYTICS_BENCH_SYMBOL").unwrap_or_else(|_| SYMBOL.to_string());
+        let hours: u64 = env::var("ANALYTICS_BENCH_HOURS")
+            .ok()
+            .and_then(|hours| hours.parse().ok())
+            .unwrap_or(1);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        targets.push((
+            "clickhouse",
+            Arc::new(ClickHouseStore::new(Client::default().with_url(url))),
+            symbol,
+            TimeRange {
+                start_micros: now.saturating_sub(hours * 3_600_000_000),
+                end_micros: now,
+            },
+        ));
+    }
+    targets
+}
+
+fn planner(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Failed to start runtime");
+    for (name, store, symbol, range) in targets() {
+        let mut group = c.benchmark_group(format!("summary/{name}"));
+        group.sample_size(10);
+        for plan in [Plan::Pushdown, Plan::InProcess] {
+            let planner = Planner::forced(store.clone(), plan);
+            group.bench_function(format!("{plan:?}"), |b| {
+                b.iter(|| runtime.block_on(planner.summary(&symbol, range)).unwrap())
+            });
+        }
+        group.finish();
+
+        let mut group = c.benchmark_group(format!("candles_1m/{name}"));
+        group.sample_size(10);
+        for plan in [Plan::Pushdown, Plan::InProcess] {
+            let planner = Planner::forced(store.clone(), plan);
+            group.bench_function(format!("{plan:?}"), |b| {
+                b.iter(|| {
+                    runtime
+                        .block_on(planner.candles(&symbol, range, Duration::from_secs(60)))
+                        .unwrap()
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, planner);
+criterion_main!(benches);
@@ -0,0 +1,225 @@
+//! Caches RPC results over historical windows. Only a window that ends
+//! before the store's ingestion watermark, less a lag for late trades, is
+//! cached: no trade can still land in it, so its result can never change.
+//! Results are kept protobuf-encoded, for at most a lifetime in case a trade
+//! lands later than the lag after all, and evicted least recently used first.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use prost::Message;
+use tonic::Status;
+
+use crate::metrics::ServerMetrics;
+use crate::store::TimeRange;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Encoded bytes kept across all entries; 0 disables the cache.
+    pub capacity: usize,
+    /// Encoded bytes of the largest result kept.
+    pub max_entry: usize,
+    /// How far behind the watermark a window must end, to allow for trades
+    /// that arrive out of order.
+    pub watermark_lag: Duration,
+    /// How long an entry is kept, bounding how long a result that missed a
+    /// late trade is served.
+    pub ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 64 * 1024 * 1024,
+            max_entry: 1024 * 1024,
+            watermark_lag: Duration::from_secs(60),
+            ttl: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub rpc: &'static str,
+    pub symbol: String,
+    pub range: TimeRange,
+    /// Every other input the result depends on, e.g. indicator periods.
+    pub params: String,
+}
+
+struct Entry {
+    value: Arc<[u8]>,
+    /// When the entry was last read or written, on [`Lru::clock`].
+    used: u64,
+    expires: Instant,
+}
+
+/// Entries by key, and keys by last use.
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<CacheKey, Entry>,
+    order: BTreeMap<u64, CacheKey>,
+    clock: u64,
+    bytes: usize,
+}
+
+impl Lru {
+    fn touch(&mut self, key: &CacheKey) -> u64 {
+        self.clock += 1;
+        self.order.insert(self.clock, key.clone());
+        self.clock
+    }
+
+    /// The value for `key`, unless it has expired, in which case it is
+    /// removed.
+    fn get(&mut self, key: &CacheKey, now: Instant) -> Option<Arc<[u8]>> {
+        let entry = self.entries.get(key)?;
+        if entry.expires <= now {
+            self.remove(key);
+            return None;
+        }
+        let used = entry.used;
+        self.order.remove(&used);
+        let used = self.touch(key);
+        let entry = self.entries.get_mut(key)?;
+        entry.used = used;
+        Some(entry.value.clone())
+    }
+
+    /// Inserts `value`, then evicts until the entries fit in `capacity`.
+    /// Returns how many were evicted.
+    fn insert(
+        &mut self,
+        key: CacheKey,
+        value: Arc<[u8]>,
+        expires: Instant,
+        capacity: usize,
+    ) -> usize {
+        self.remove(&key);
+        let used = self.touch(&key);
+        self.bytes += value.len();
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                used,
+                expires,
+            },
+        );
+        let mut evicted = 0;
+        while self.bytes > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.bytes -= entry.value.len();
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+            self.bytes -= entry.value.len();
+        }
+    }
+}
+
+pub struct ResultCache {
+    config: CacheConfig,
+    metrics: Arc<ServerMetrics>,
+    lru: Mutex<Lru>,
+}
+
+impl ResultCache {
+    pub fn new(config: CacheConfig, metrics: Arc<ServerMetrics>) -> Self {
+        Self {
+            config,
+            metrics,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Whether no trade can still land in `range`, given the store's
+    /// `watermark`.
+    pub fn closed(&self, range: TimeRange, watermark: Option<u64>) -> bool {
+        let lag = self.config.watermark_lag.as_micros() as u64;
+        match watermark {
+            Some(watermark) => range.end_micros.saturating_add(lag) < watermark,
+            None => false,
+        }
+    }
+
+    /// The cached result for `key`, or the one `compute` returns. `watermark`
+    /// is only awaited on a miss, before `compute`, so that everything up to
+    /// it is in the result; the result is kept if `key`'s window is
+    /// [`closed`](Self::closed) at that watermark. Errors are never cached.
+    pub async fn get_or_compute<M, W, F>(
+        &self,
+        key: CacheKey,
+        watermark: W,
+        compute: F,
+    ) -> Result<M, Status>
+    where
+        M: Message + Default,
+        W: Future<Output = Result<Option<u64>, Status>>,
+        F: Future<Output = Result<M, Status>>,
+    {
+        if self.config.capacity == 0 {
+            return compute.await;
+        }
+        let cached = self.lru.lock().unwrap().get(&key, Instant::now());
+        if let Some(bytes) = cached {
+            self.count(&key, "hit");
+            return M::decode(&bytes[..])
+                .map_err(|e| Status::internal(format!("Error decoding cached result: {}", e)));
+        }
+
+        let closed = match watermark.await {
+            Ok(watermark) => self.closed(key.range, watermark),
+            Err(e) => {
+                println!(
+                    "Not caching {} for {}: {}",
+                    key.rpc,
+                    key.symbol,
+                    e.message()
+                );
+                false
+            }
+        };
+        if !closed {
+            self.count(&key, "bypass");
+            return compute.await;
+        }
+        self.count(&key, "miss");
+        let result = compute.await?;
+        let bytes = result.encoded_len();
+        if bytes > self.config.max_entry || bytes > self.config.capacity {
+            self.metrics.cache_oversized.inc();
+            return Ok(result);
+        }
+        let mut lru = self.lru.lock().unwrap();
+        let expires = Instant::now() + self.config.ttl;
+        let evicted = lru.insert(
+            key,
+            result.encode_to_vec().into(),
+            expires,
+            self.config.capacity,
+        );
+        self.metrics.cache_evictions.inc_by(evicted as u64);
+        self.metrics.cache_entries.set(lru.entries.len() as i64);
+        self.metrics.cache_bytes.set(lru.bytes as i64);
+        Ok(result)
+    }
+
+    fn count(&self, key: &CacheKey, result: &str) {
+        self.metrics
+            .cache_requests
+            .with_label_values(&[key.rpc, result])
+            .inc();
+    }
+}
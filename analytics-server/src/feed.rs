@@ -0,0 +1,70 @@
+use async_nats::Client as AsyncNatsClient;
+use async_trait::async_trait;
+use futures::StreamExt;
+use futures::stream::{self, BoxStream};
+use prost::Message;
+use tonic::Status;
+
+use crate::data;
+
+pub type TradeStream = BoxStream<'static, Result<data::Trade, Status>>;
+
+/// Source of live trades for `SubscribeToTrades`.
+#[async_trait]
+pub trait TradeFeed: Send + Sync {
+    async fn subscribe(&self, symbol: &str) -> Result<TradeStream, Status>;
+}
+
+/// Subscribes to `trades.<exchange>.<symbol>` on NATS across every exchange.
+pub struct NatsFeed {
+    client: AsyncNatsClient,
+}
+
+impl NatsFeed {
+    pub fn new(client: AsyncNatsClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl TradeFeed for NatsFeed {
+    async fn subscribe(&self, symbol: &str) -> Result<TradeStream, Status> {
+        let subject = format!("trades.*.{}", symbol.to_lowercase());
+        let subscription = self
+            .client
+            .subscribe(subject)
+            .await
+            .map_err(|e| Status::internal(format!("Error subscribing to subject: {}", e)))?;
+        let trade_stream = subscription.map(|msg| {
+            data::Trade::decode(msg.payload)
+                .map_err(|e| Status::internal(format!("Failed to decode trade data: {}", e)))
+        });
+        Ok(Box::pin(trade_stream))
+    }
+}
+
+/// Replays a fixed list of trades to every subscriber, then ends the stream.
+#[derive(Debug, Default)]
+pub struct MemoryFeed {
+    trades: Vec<data::Trade>,
+}
+
+impl MemoryFeed {
+    pub fn new(trades: Vec<data::Trade>) -> Self {
+        Self { trades }
+    }
+}
+
+#[async_trait]
+impl TradeFeed for MemoryFeed {
+    async fn subscribe(&self, symbol: &str) -> Result<TradeStream, Status> {
+        let trades: Vec<_> = self
+            .trades
+            .iter()
+            .filter(|t| t.symbol.eq_ignore_ascii_case(symbol))
+            .cloned()
+            .map(Ok)
+            .collect();
+        Ok(Box::pin(stream::iter(trades)))
+    }
+}
@@ -0,0 +1,455 @@
+//! Arrow Flight access to trades, candles, indicator outputs and ad-hoc SQL,
+//! so pandas, Polars or DuckDB can read columns straight off the wire. Served
+//! next to the gRPC service on the same port.
+//!
+//! Tickets and command descriptors are JSON commands:
+//!
+//! - `{"trades": {"symbol": "BTCUSDT", "start": 1700000000000000}}`
+//! - `{"candles": {"symbol": "BTCUSDT", "interval_secs": 300}}`
+//! - `{"indicator": {"name": "macd", "symbol": "BTCUSDT", "interval_secs": 60,
+//!   "params": {"fast_period": 8}}}`
+//! - `{"query": "SELECT * FROM candles WHERE symbol = 'BTCUSDT'"}`
+//!
+//! `start` and `end` are inclusive, in microseconds since the epoch, and
+//! default to the whole history. Trades stream straight from the store; SQL
+//! queries run under the same limits as `ExecuteQuery`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::arrow::ipc::writer::{
+    DictionaryTracker, EncodedData, IpcDataGenerator, IpcWriteOptions, write_message,
+};
+use datafusion::prelude::{col, lit};
+use futures::StreamExt;
+use futures::stream::{self, BoxStream};
+use serde::Deserialize;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::analytics::{ComputeIndicatorRequest, ComputeIndicatorResponse, SeriesType};
+use crate::arrow_flight::flight_descriptor::DescriptorType;
+use crate::arrow_flight::flight_service_server::FlightService;
+use crate::arrow_flight::{
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PutResult, SchemaResult, Ticket,
+};
+use crate::planner::CANDLES_TABLE_INTERVAL;
+use crate::query;
+use crate::service::AnalyticsServiceHandler;
+use crate::store::TimeRange;
+use crate::store::table::{candles_batch, candles_schema, trades_schema};
+
+/// Rows sent in one `FlightData` message at most.
+const MESSAGE_ROWS: usize = 65_536;
+
+/// What a ticket or command descriptor asks for.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Command {
+    Trades(TradesCommand),
+    Candles(CandlesCommand),
+    Indicator(IndicatorCommand),
+    /// SQL over the `trades` and `candles` tables.
+    Query(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct Range {
+    #[serde(default)]
+    start: u64,
+    #[serde(default = "end_of_time")]
+    end: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradesCommand {
+    /// Every symbol when unset.
+    symbol: Option<String>,
+    #[serde(flatten)]
+    range: Range,
+}
+
+#[derive(Debug, Deserialize)]
+struct CandlesCommand {
+    symbol: String,
+    #[serde(default = "default_interval")]
+    interval_secs: u64,
+    #[serde(flatten)]
+    range: Range,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndicatorCommand {
+    name: String,
+    symbol: String,
+    #[serde(default)]
+    params: HashMap<String, f64>,
+    /// Runs over trade prices when unset, as in `ComputeIndicator`.
+    interval_secs: Option<u64>,
+    /// `close`, `open`, `high` or `low`.
+    series_type: Option<String>,
+    #[serde(flatten)]
+    range: Range,
+}
+
+/// Whole seconds as a protobuf duration, saturating at its range so overlong
+/// ones are rejected as such.
+fn seconds(seconds: u64) -> prost_types::Duration {
+    prost_types::Duration {
+        seconds: i64::try_from(seconds).unwrap_or(i64::MAX),
+        nanos: 0,
+    }
+}
+
+fn end_of_time() -> u64 {
+    u64::MAX
+}
+
+fn default_interval() -> u64 {
+    CANDLES_TABLE_INTERVAL.as_secs()
+}
+
+impl Command {
+    fn parse(bytes: &[u8]) -> Result<Self, Status> {
+        serde_json::from_slice(bytes)
+            .map_err(|e| Status::invalid_argument(format!("Invalid Flight command: {}", e)))
+    }
+}
+
+impl Range {
+    fn time_range(&self) -> Result<TimeRange, Status> {
+        if self.start > self.end {
+            return Err(Status::invalid_argument("start must not be after end"));
+        }
+        Ok(TimeRange {
+            start_micros: self.start,
+            end_micros: self.end,
+        })
+    }
+}
+
+impl IndicatorCommand {
+    fn request(&self) -> Result<ComputeIndicatorRequest, Status> {
+        let series_type = match &self.series_type {
+            None => SeriesType::Close,
+            Some(name) => {
+                SeriesType::from_str_name(&format!("SERIES_TYPE_{}", name.to_uppercase()))
+                    .ok_or_else(|| {
+                        Status::invalid_argument(format!("Unknown series type `{name}`"))
+                    })?
+            }
+        };
+        let range = self.range.time_range()?;
+        Ok(ComputeIndicatorRequest {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            symbol: self.symbol.clone(),
+            start_timestamp: Some(timestamp(range.start_micros)),
+            end_timestamp: Some(timestamp(range.end_micros)),
+            interval: self.interval_secs.map(seconds),
+            series_type: series_type as i32,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+    }
+}
+
+fn timestamp(micros: u64) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: (micros / 1_000_000) as i64,
+        nanos: (micros % 1_000_000 * 1_000) as i32,
+    }
+}
+
+/// `timestamp` in microseconds, then one column per indicator output.
+fn indicator_schema(outputs: &[impl AsRef<str>]) -> SchemaRef {
+    let mut fields = vec![Field::new("timestamp", DataType::Int64, false)];
+    fields.extend(
+        outputs
+            .iter()
+            .map(|output| Field::new(output.as_ref(), DataType::Float64, false)),
+    );
+    Arc::new(Schema::new(fields))
+}
+
+fn indicator_batch(response: ComputeIndicatorResponse) -> Result<RecordBatch, Status> {
+    let schema = indicator_schema(&response.outputs);
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(Int64Array::from_iter_values(
+        response.points.iter().map(|p| p.timestamp as i64),
+    ))];
+    columns.extend((0..response.outputs.len()).map(|i| {
+        Arc::new(Float64Array::from_iter_values(
+            response.points.iter().map(|p| p.values[i]),
+        )) as ArrayRef
+    }));
+    RecordBatch::try_new(schema, columns).map_err(internal)
+}
+
+type Batches = BoxStream<'static, Result<RecordBatch, Status>>;
+
+/// Serves the analytics handler's data over Arrow Flight.
+pub struct AnalyticsFlightService {
+    handler: Arc<AnalyticsServiceHandler>,
+}
+
+impl AnalyticsFlightService {
+    pub fn new(handler: Arc<AnalyticsServiceHandler>) -> Self {
+        Self { handler }
+    }
+
+    async fn schema(&self, command: &Command) -> Result<SchemaRef, Status> {
+        match command {
+            Command::Trades(_) => Ok(trades_schema()),
+            Command::Candles(_) => Ok(candles_schema()),
+            Command::Indicator(command) => match self.handler.registry().get(&command.name) {
+                Some(indicator) => Ok(indicator_schema(indicator.outputs())),
+                None => Err(Status::not_found(format!(
+                    "Unknown indicator `{}`",
+                    command.name
+                ))),
+            },
+            Command::Query(sql) => query::schema(self.handler.planner().context(), sql).await,
+        }
+    }
+
+    async fn batches(&self, command: Command) -> Result<(SchemaRef, Batches), Status> {
+        let planner = self.handler.planner();
+        match command {
+            Command::Trades(command) => {
+                let range = command.range.time_range()?;
+                let bound = |micros: u64| lit(i64::try_from(micros).unwrap_or(i64::MAX));
+                let mut filter = col("exchange_timestamp")
+                    .between(bound(range.start_micros), bound(range.end_micros));
+                if let Some(symbol) = command.symbol {
+                    filter = filter.and(col("symbol").eq(lit(symbol)));
+                }
+                let stream = planner
+                    .context()
+                    .table("trades")
+                    .await
+                    .and_then(|df| df.filter(filter))
+                    .map_err(internal)?
+                    .execute_stream()
+                    .await
+                    .map_err(internal)?;
+                let schema = stream.schema();
+                Ok((
+                    schema,
+                    Box::pin(stream.map(|batch| batch.map_err(internal))),
+                ))
+            }
+            Command::Candles(command) => {
+                let range = command.range.time_range()?;
+                let interval =
+                    AnalyticsServiceHandler::interval(Some(&seconds(command.interval_secs)))?;
+                let candles = planner.candles(&command.symbol, range, interval).await?;
+                let batch = candles_batch(&command.symbol, &candles).map_err(internal)?;
+                Ok((batch.schema(), Box::pin(stream::iter([Ok(batch)]))))
+            }
+            Command::Indicator(command) => {
+                let response = self.handler.indicator_response(&command.request()?).await?;
+                let batch = indicator_batch(response)?;
+                Ok((batch.schema(), Box::pin(stream::iter([Ok(batch)]))))
+            }
+            Command::Query(sql) => {
+                let result =
+                    query::run(planner.context(), &sql, self.handler.query_limits()).await?;
+                let batches = result.batches.into_iter().map(Ok).collect::<Vec<_>>();
+                Ok((result.schema, Box::pin(stream::iter(batches))))
+            }
+        }
+    }
+
+    /// Describes a command descriptor; its one endpoint is fetched from this
+    /// server with the command as the ticket.
+    async fn flight_info(&self, descriptor: FlightDescriptor) -> Result<FlightInfo, Status> {
+        if descriptor.r#type() != DescriptorType::Cmd {
+            return Err(Status::invalid_argument(
+                "Only command descriptors can be fetched",
+            ));
+        }
+        let schema = self.schema(&Command::parse(&descriptor.cmd)?).await?;
+        Ok(FlightInfo {
+            schema: ipc_schema(&schema)?,
+            endpoint: vec![FlightEndpoint {
+                ticket: Some(Ticket {
+                    ticket: descriptor.cmd.clone(),
+                }),
+                ..Default::default()
+            }],
+            flight_descriptor: Some(descriptor),
+            total_records: -1,
+            total_bytes: -1,
+            ordered: false,
+            app_metadata: Vec::new(),
+        })
+    }
+}
+
+/// A path descriptor naming what a command can fetch, with its schema.
+fn listing(path: &[&str], schema: &Schema) -> Result<FlightInfo, Status> {
+    Ok(FlightInfo {
+        schema: ipc_schema(schema)?,
+        flight_descriptor: Some(FlightDescriptor {
+            r#type: DescriptorType::Path as i32,
+            cmd: Vec::new(),
+            path: path.iter().map(|p| p.to_string()).collect(),
+        }),
+        total_records: -1,
+        total_bytes: -1,
+        ..Default::default()
+    })
+}
+
+/// `schema` as an encapsulated IPC message, as `FlightInfo` and
+/// `SchemaResult` carry it.
+fn ipc_schema(schema: &Schema) -> Result<Vec<u8>, Status> {
+    let options = IpcWriteOptions::default();
+    let encoded = IpcDataGenerator::default().schema_to_bytes_with_dictionary_tracker(
+        schema,
+        &mut DictionaryTracker::new(false),
+        &options,
+    );
+    let mut bytes = Vec::new();
+    write_message(&mut bytes, encoded, &options).map_err(internal)?;
+    Ok(bytes)
+}
+
+fn flight_data(encoded: EncodedData) -> FlightData {
+    FlightData {
+        data_header: encoded.ipc_message,
+        data_body: encoded.arrow_data,
+        ..Default::default()
+    }
+}
+
+/// The schema message followed by the batches, split into messages of at most
+/// [`MESSAGE_ROWS`] rows.
+fn encode(
+    schema: SchemaRef,
+    mut batches: Batches,
+) -> BoxStream<'static, Result<FlightData, Status>> {
+    let data = async_stream::try_stream! {
+        let generator = IpcDataGenerator::default();
+        let options = IpcWriteOptions::default();
+        let mut tracker = DictionaryTracker::new(false);
+        yield flight_data(generator.schema_to_bytes_with_dictionary_tracker(
+            &schema,
+            &mut tracker,
+            &options,
+        ));
+        while let Some(batch) = batches.next().await {
+            let batch = batch?;
+            for offset in (0..batch.num_rows()).step_by(MESSAGE_ROWS) {
+                let length = MESSAGE_ROWS.min(batch.num_rows() - offset);
+                let (dictionaries, message) = generator
+                    .encoded_batch(&batch.slice(offset, length), &mut tracker, &options)
+                    .map_err(internal)?;
+                for dictionary in dictionaries {
+                    yield flight_data(dictionary);
+                }
+                yield flight_data(message);
+            }
+        }
+    };
+    Box::pin(data)
+}
+
+fn internal(error: impl std::fmt::Display) -> Status {
+    Status::internal(format!("Error serving Flight data: {}", error))
+}
+
+#[tonic::async_trait]
+impl FlightService for AnalyticsFlightService {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented(
+            "The Flight service needs no handshake",
+        ))
+    }
+
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let mut flights = vec![
+            listing(&["trades"], &trades_schema()),
+            listing(&["candles"], &candles_schema()),
+        ];
+        flights.extend(self.handler.registry().iter().map(|indicator| {
+            listing(
+                &["indicator", indicator.name()],
+                &indicator_schema(indicator.outputs()),
+            )
+        }));
+        Ok(Response::new(Box::pin(stream::iter(flights))))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let info = self.flight_info(request.into_inner()).await?;
+        Ok(Response::new(info))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let info = self.flight_info(request.into_inner()).await?;
+        Ok(Response::new(SchemaResult {
+            schema: info.schema,
+        }))
+    }
+
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let command = Command::parse(&request.into_inner().ticket)?;
+        println!("Received Flight request: {:?}", command);
+        let (schema, batches) = self.batches(command).await?;
+        Ok(Response::new(encode(schema, batches)))
+    }
+
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("The Flight service is read-only"))
+    }
+
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("The Flight service is read-only"))
+    }
+
+    type DoActionStream = BoxStream<'static, Result<crate::arrow_flight::Result, Status>>;
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("The Flight service has no actions"))
+    }
+
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(Box::pin(stream::empty())))
+    }
+}
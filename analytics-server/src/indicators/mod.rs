@@ -0,0 +1,140 @@
+//! Indicator math over plain slices. Each function documents which input index
+//! its first output belongs to; callers use that to attach timestamps.
+
+pub mod streaming;
+
+use tonic::Status;
+
+use crate::store::Candle;
+use streaming::{
+    AtrState, BollingerState, EmaState, Hlc, KeltnerState, MacdState, RsiState, SmaState, Streaming,
+};
+
+/// Upper, middle and lower line of a price envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub upper: f64,
+    pub middle: f64,
+    pub lower: f64,
+}
+
+/// One MACD row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Macd {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+/// Simple moving average of each full window. The first value belongs to
+/// `values[window_size - 1]`.
+pub fn sma(values: &[f64], window_size: usize) -> Vec<f64> {
+    SmaState::new(window_size).run(values)
+}
+
+/// Exponential moving average seeded with the SMA of the first `window_size`
+/// values. The first value belongs to `values[window_size - 1]`.
+pub fn ema(values: &[f64], window_size: usize) -> Vec<f64> {
+    EmaState::new(window_size).run(values)
+}
+
+/// Validated MACD periods: all positive and `fast < slow`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacdPeriods {
+    fast: usize,
+    slow: usize,
+    signal: usize,
+}
+
+impl MacdPeriods {
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Result<Self, Status> {
+        if fast == 0 || slow == 0 || signal == 0 {
+            return Err(Status::invalid_argument("MACD periods must be positive"));
+        }
+        if fast >= slow {
+            return Err(Status::invalid_argument(
+                "fast_period must be less than slow_period",
+            ));
+        }
+        Ok(Self { fast, slow, signal })
+    }
+
+    pub fn fast(&self) -> usize {
+        self.fast
+    }
+
+    pub fn slow(&self) -> usize {
+        self.slow
+    }
+
+    pub fn signal(&self) -> usize {
+        self.signal
+    }
+
+    /// Index of the value the first MACD row belongs to: the slow EMA's
+    /// warm-up plus the signal EMA's.
+    pub fn warm_up(&self) -> usize {
+        (self.slow - 1) + (self.signal - 1)
+    }
+}
+
+/// MACD as TA-Lib's `TA_MACD` computes it. Both EMAs start at
+/// `values[slow - 1]`, each seeded with the SMA of its period ending there, so
+/// the MACD line is the difference of two EMAs of the same value. The signal
+/// is an EMA of that line seeded the same way. Row `i` belongs to
+/// `values[periods.warm_up() + i]`.
+pub fn macd(values: &[f64], periods: MacdPeriods) -> Vec<Macd> {
+    MacdState::new(periods).run(values)
+}
+
+/// Wilder's RSI: the first average gain and loss are plain means over the
+/// first `window_size` changes, later ones are smoothed as
+/// `(previous * (window_size - 1) + current) / window_size`. The first value
+/// belongs to `values[window_size]`. A window with no losses reads 100, and
+/// one with no movement at all reads 50.
+pub fn rsi(values: &[f64], window_size: usize) -> Vec<f64> {
+    RsiState::new(window_size).run(values)
+}
+
+/// SMA of each full window plus and minus `num_std_dev` population standard
+/// deviations. The first band belongs to `values[window_size - 1]`.
+pub fn bollinger_bands(values: &[f64], window_size: usize, num_std_dev: f64) -> Vec<Band> {
+    BollingerState::new(window_size, num_std_dev).run(values)
+}
+
+/// True range of each candle after the first: the widest of high - low and the
+/// gaps from the previous close. The first value belongs to `candles[1]`.
+pub fn true_range(candles: &[Candle]) -> Vec<f64> {
+    candles
+        .windows(2)
+        .map(|pair| Hlc::from(&pair[1]).true_range(pair[0].close))
+        .collect()
+}
+
+/// Average True Range with Wilder smoothing, seeded with the mean of the first
+/// `window_size` true ranges as TA-Lib does. The first value belongs to
+/// `candles[window_size]`.
+pub fn atr(candles: &[Candle], window_size: usize) -> Vec<f64> {
+    AtrState::new(window_size).run(&hlc(candles))
+}
+
+/// EMA of closes over `ema_window` plus and minus `multiplier` times the ATR
+/// over `atr_window`. The first band belongs to
+/// `candles[keltner_warm_up(ema_window, atr_window)]`.
+pub fn keltner_channels(
+    candles: &[Candle],
+    ema_window: usize,
+    atr_window: usize,
+    multiplier: f64,
+) -> Vec<Band> {
+    KeltnerState::new(ema_window, atr_window, multiplier).run(&hlc(candles))
+}
+
+fn hlc(candles: &[Candle]) -> Vec<Hlc> {
+    candles.iter().map(Hlc::from).collect()
+}
+
+/// Index of the first candle with both an EMA and an ATR value.
+pub fn keltner_warm_up(ema_window: usize, atr_window: usize) -> usize {
+    (ema_window - 1).max(atr_window)
+}
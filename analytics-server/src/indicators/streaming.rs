@@ -0,0 +1,578 @@
+//! Indicators as state machines fed one value or candle at a time, each update
+//! O(1), which the parent module's batch functions run over a slice so batch
+//! and live results match exactly. A snapshot captures a machine's whole state,
+//! so a stream can be paused and resumed elsewhere.
+
+use std::collections::VecDeque;
+
+use super::{Band, Macd, MacdPeriods};
+use crate::store::Candle;
+
+pub trait Streaming: Sized {
+    /// What each update reads: a value, or a candle's [`Hlc`].
+    type Input: Copy;
+    type Output;
+    type Snapshot;
+
+    /// Feeds the next input, returning an output once warmed up.
+    fn update(&mut self, input: Self::Input) -> Option<Self::Output>;
+    fn snapshot(&self) -> Self::Snapshot;
+    fn restore(snapshot: Self::Snapshot) -> Self;
+
+    /// Feeds every input and collects the outputs.
+    fn run(&mut self, inputs: &[Self::Input]) -> Vec<Self::Output> {
+        inputs
+            .iter()
+            .filter_map(|input| self.update(*input))
+            .collect()
+    }
+}
+
+/// The parts of a candle true-range indicators read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hlc {
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+impl Hlc {
+    /// The widest of high - low and the gaps from the previous close.
+    pub fn true_range(&self, previous_close: f64) -> f64 {
+        (self.high - self.low)
+            .max((self.high - previous_close).abs())
+            .max((self.low - previous_close).abs())
+    }
+}
+
+impl From<&Candle> for Hlc {
+    fn from(candle: &Candle) -> Self {
+        Self {
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+        }
+    }
+}
+
+/// Simple moving average over a running sum.
+#[derive(Debug, Clone)]
+pub struct SmaState {
+    window: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmaSnapshot {
+    pub window: usize,
+    pub values: Vec<f64>,
+    pub sum: f64,
+}
+
+impl SmaState {
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "SMA window must be positive");
+        Self {
+            window,
+            values: VecDeque::with_capacity(window + 1),
+            sum: 0.0,
+        }
+    }
+
+    /// Mean of the values seen so far in the current window, full or not.
+    pub fn average(&self) -> Option<f64> {
+        (!self.values.is_empty()).then(|| self.sum / self.values.len() as f64)
+    }
+}
+
+impl Streaming for SmaState {
+    type Input = f64;
+    type Output = f64;
+    type Snapshot = SmaSnapshot;
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        self.values.push_back(value);
+        self.sum += value;
+        if self.values.len() > self.window {
+            self.sum -= self.values.pop_front().unwrap_or_default();
+        }
+        (self.values.len() == self.window).then(|| self.sum / self.window as f64)
+    }
+
+    fn snapshot(&self) -> SmaSnapshot {
+        SmaSnapshot {
+            window: self.window,
+            values: self.values.iter().copied().collect(),
+            sum: self.sum,
+        }
+    }
+
+    fn restore(snapshot: SmaSnapshot) -> Self {
+        Self {
+            window: snapshot.window,
+            values: snapshot.values.into(),
+            sum: snapshot.sum,
+        }
+    }
+}
+
+/// Exponential moving average seeded with the SMA of the first window.
+#[derive(Debug, Clone)]
+pub struct EmaState {
+    window: usize,
+    multiplier: f64,
+    count: usize,
+    sum: f64,
+    value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmaSnapshot {
+    pub window: usize,
+    /// Values summed towards the seed, until it is reached.
+    pub count: usize,
+    pub sum: f64,
+    pub value: Option<f64>,
+}
+
+impl EmaState {
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "EMA window must be positive");
+        Self {
+            window,
+            multiplier: 2.0 / (window as f64 + 1.0),
+            count: 0,
+            sum: 0.0,
+            value: None,
+        }
+    }
+}
+
+impl Streaming for EmaState {
+    type Input = f64;
+    type Output = f64;
+    type Snapshot = EmaSnapshot;
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        match self.value {
+            Some(last) => {
+                self.value = Some((value * self.multiplier) + (last * (1.0 - self.multiplier)));
+            }
+            None => {
+                self.count += 1;
+                self.sum += value;
+                if self.count == self.window {
+                    self.value = Some(self.sum / self.window as f64);
+                }
+            }
+        }
+        self.value
+    }
+
+    fn snapshot(&self) -> EmaSnapshot {
+        EmaSnapshot {
+            window: self.window,
+            count: self.count,
+            sum: self.sum,
+            value: self.value,
+        }
+    }
+
+    fn restore(snapshot: EmaSnapshot) -> Self {
+        Self {
+            count: snapshot.count,
+            sum: snapshot.sum,
+            value: snapshot.value,
+            ..Self::new(snapshot.window)
+        }
+    }
+}
+
+/// MACD line from a fast and a slow EMA, and a signal EMA of that line. The
+/// fast EMA skips the first `slow - fast` values so both are seeded over
+/// windows ending at the same value, as TA-Lib does.
+#[derive(Debug, Clone)]
+pub struct MacdState {
+    fast_delay: usize,
+    fast: EmaState,
+    slow: EmaState,
+    signal: EmaState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdSnapshot {
+    /// Values still to skip before the fast EMA starts.
+    pub fast_delay: usize,
+    pub fast: EmaSnapshot,
+    pub slow: EmaSnapshot,
+    pub signal: EmaSnapshot,
+}
+
+impl MacdState {
+    pub fn new(periods: MacdPeriods) -> Self {
+        Self {
+            fast_delay: periods.slow() - periods.fast(),
+            fast: EmaState::new(periods.fast()),
+            slow: EmaState::new(periods.slow()),
+            signal: EmaState::new(periods.signal()),
+        }
+    }
+}
+
+impl Streaming for MacdState {
+    type Input = f64;
+    type Output = Macd;
+    type Snapshot = MacdSnapshot;
+
+    fn update(&mut self, value: f64) -> Option<Macd> {
+        let slow = self.slow.update(value);
+        let fast = if self.fast_delay > 0 {
+            self.fast_delay -= 1;
+            None
+        } else {
+            self.fast.update(value)
+        };
+        let macd = fast? - slow?;
+        let signal = self.signal.update(macd)?;
+        Some(Macd {
+            macd,
+            signal,
+            histogram: macd - signal,
+        })
+    }
+
+    fn snapshot(&self) -> MacdSnapshot {
+        MacdSnapshot {
+            fast_delay: self.fast_delay,
+            fast: self.fast.snapshot(),
+            slow: self.slow.snapshot(),
+            signal: self.signal.snapshot(),
+        }
+    }
+
+    fn restore(snapshot: MacdSnapshot) -> Self {
+        Self {
+            fast_delay: snapshot.fast_delay,
+            fast: EmaState::restore(snapshot.fast),
+            slow: EmaState::restore(snapshot.slow),
+            signal: EmaState::restore(snapshot.signal),
+        }
+    }
+}
+
+/// Wilder's RSI: plain means of the first `window` gains and losses, then
+/// each smoothed as `(previous * (window - 1) + current) / window`.
+#[derive(Debug, Clone)]
+pub struct RsiState {
+    window: usize,
+    previous: Option<f64>,
+    /// Changes seen, up to `window`.
+    count: usize,
+    gain: f64,
+    loss: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RsiSnapshot {
+    pub window: usize,
+    pub previous: Option<f64>,
+    pub count: usize,
+    /// Sums until `count` reaches `window`, averages after.
+    pub gain: f64,
+    pub loss: f64,
+}
+
+impl RsiState {
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "RSI window must be positive");
+        Self {
+            window,
+            previous: None,
+            count: 0,
+            gain: 0.0,
+            loss: 0.0,
+        }
+    }
+
+    /// 100 with no losses, and 50 with no movement at all.
+    fn value(&self) -> f64 {
+        if self.loss == 0.0 {
+            if self.gain == 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + self.gain / self.loss)
+        }
+    }
+}
+
+impl Streaming for RsiState {
+    type Input = f64;
+    type Output = f64;
+    type Snapshot = RsiSnapshot;
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        let change = value - self.previous.replace(value)?;
+        let period = self.window as f64;
+        if self.count < self.window {
+            self.count += 1;
+            self.gain += change.max(0.0);
+            self.loss += (-change).max(0.0);
+            if self.count < self.window {
+                return None;
+            }
+            self.gain /= period;
+            self.loss /= period;
+        } else {
+            self.gain = (self.gain * (period - 1.0) + change.max(0.0)) / period;
+            self.loss = (self.loss * (period - 1.0) + (-change).max(0.0)) / period;
+        }
+        Some(self.value())
+    }
+
+    fn snapshot(&self) -> RsiSnapshot {
+        RsiSnapshot {
+            window: self.window,
+            previous: self.previous,
+            count: self.count,
+            gain: self.gain,
+            loss: self.loss,
+        }
+    }
+
+    fn restore(snapshot: RsiSnapshot) -> Self {
+        Self {
+            window: snapshot.window,
+            previous: snapshot.previous,
+            count: snapshot.count,
+            gain: snapshot.gain,
+            loss: snapshot.loss,
+        }
+    }
+}
+
+/// SMA of the window plus and minus a multiple of its population standard
+/// deviation. The squared deviations are kept summed as values enter and leave
+/// the window (Welford's update), and summed afresh once every `window`
+/// updates so rounding cannot build up: O(1) per update, amortized.
+#[derive(Debug, Clone)]
+pub struct BollingerState {
+    num_std_dev: f64,
+    sma: SmaState,
+    /// Sum of the squared deviations of the window from its mean.
+    m2: f64,
+    /// Updates since `m2` was last summed afresh.
+    drift: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BollingerSnapshot {
+    pub num_std_dev: f64,
+    pub sma: SmaSnapshot,
+    pub m2: f64,
+    pub drift: usize,
+}
+
+impl BollingerState {
+    /// Panics if `window` is zero.
+    pub fn new(window: usize, num_std_dev: f64) -> Self {
+        Self {
+            num_std_dev,
+            sma: SmaState::new(window),
+            m2: 0.0,
+            drift: 0,
+        }
+    }
+}
+
+impl Streaming for BollingerState {
+    type Input = f64;
+    type Output = Band;
+    type Snapshot = BollingerSnapshot;
+
+    fn update(&mut self, value: f64) -> Option<Band> {
+        let old_mean = self.sma.average();
+        let leaving = (self.sma.values.len() == self.sma.window)
+            .then(|| self.sma.values.front().copied())
+            .flatten();
+        let full = self.sma.update(value);
+        let mean = self.sma.average().unwrap_or(value);
+        self.m2 += match (old_mean, leaving) {
+            (Some(old_mean), Some(leaving)) => {
+                (value - leaving) * (value - mean + leaving - old_mean)
+            }
+            (Some(old_mean), None) => (value - old_mean) * (value - mean),
+            (None, _) => 0.0,
+        };
+        full?;
+        self.drift += 1;
+        if self.drift >= self.sma.window {
+            self.m2 = self.sma.values.iter().map(|v| (v - mean).powi(2)).sum();
+            self.drift = 0;
+        }
+        let variance = self.m2.max(0.0) / self.sma.window as f64;
+        let width = self.num_std_dev * variance.sqrt();
+        Some(Band {
+            upper: mean + width,
+            middle: mean,
+            lower: mean - width,
+        })
+    }
+
+    fn snapshot(&self) -> BollingerSnapshot {
+        BollingerSnapshot {
+            num_std_dev: self.num_std_dev,
+            sma: self.sma.snapshot(),
+            m2: self.m2,
+            drift: self.drift,
+        }
+    }
+
+    fn restore(snapshot: BollingerSnapshot) -> Self {
+        Self {
+            num_std_dev: snapshot.num_std_dev,
+            sma: SmaState::restore(snapshot.sma),
+            m2: snapshot.m2,
+            drift: snapshot.drift,
+        }
+    }
+}
+
+/// Average True Range with Wilder smoothing, seeded with the mean of the
+/// first `window` true ranges. The first candle only supplies a close.
+#[derive(Debug, Clone)]
+pub struct AtrState {
+    window: usize,
+    previous_close: Option<f64>,
+    count: usize,
+    sum: f64,
+    value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtrSnapshot {
+    pub window: usize,
+    pub previous_close: Option<f64>,
+    /// True ranges summed towards the seed, until it is reached.
+    pub count: usize,
+    pub sum: f64,
+    pub value: Option<f64>,
+}
+
+impl AtrState {
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "ATR window must be positive");
+        Self {
+            window,
+            previous_close: None,
+            count: 0,
+            sum: 0.0,
+            value: None,
+        }
+    }
+}
+
+impl Streaming for AtrState {
+    type Input = Hlc;
+    type Output = f64;
+    type Snapshot = AtrSnapshot;
+
+    fn update(&mut self, candle: Hlc) -> Option<f64> {
+        let range = candle.true_range(self.previous_close.replace(candle.close)?);
+        let period = self.window as f64;
+        match self.value {
+            Some(last) => self.value = Some((last * (period - 1.0) + range) / period),
+            None => {
+                self.count += 1;
+                self.sum += range;
+                if self.count == self.window {
+                    self.value = Some(self.sum / period);
+                }
+            }
+        }
+        self.value
+    }
+
+    fn snapshot(&self) -> AtrSnapshot {
+        AtrSnapshot {
+            window: self.window,
+            previous_close: self.previous_close,
+            count: self.count,
+            sum: self.sum,
+            value: self.value,
+        }
+    }
+
+    fn restore(snapshot: AtrSnapshot) -> Self {
+        Self {
+            window: snapshot.window,
+            previous_close: snapshot.previous_close,
+            count: snapshot.count,
+            sum: snapshot.sum,
+            value: snapshot.value,
+        }
+    }
+}
+
+/// EMA of closes plus and minus a multiple of the ATR, once both are warmed
+/// up.
+#[derive(Debug, Clone)]
+pub struct KeltnerState {
+    multiplier: f64,
+    ema: EmaState,
+    atr: AtrState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeltnerSnapshot {
+    pub multiplier: f64,
+    pub ema: EmaSnapshot,
+    pub atr: AtrSnapshot,
+}
+
+impl KeltnerState {
+    /// Panics if either window is zero.
+    pub fn new(ema_window: usize, atr_window: usize, multiplier: f64) -> Self {
+        Self {
+            multiplier,
+            ema: EmaState::new(ema_window),
+            atr: AtrState::new(atr_window),
+        }
+    }
+}
+
+impl Streaming for KeltnerState {
+    type Input = Hlc;
+    type Output = Band;
+    type Snapshot = KeltnerSnapshot;
+
+    fn update(&mut self, candle: Hlc) -> Option<Band> {
+        let middle = self.ema.update(candle.close);
+        let atr = self.atr.update(candle);
+        let (middle, atr) = (middle?, atr?);
+        Some(Band {
+            upper: middle + self.multiplier * atr,
+            middle,
+            lower: middle - self.multiplier * atr,
+        })
+    }
+
+    fn snapshot(&self) -> KeltnerSnapshot {
+        KeltnerSnapshot {
+            multiplier: self.multiplier,
+            ema: self.ema.snapshot(),
+            atr: self.atr.snapshot(),
+        }
+    }
+
+    fn restore(snapshot: KeltnerSnapshot) -> Self {
+        Self {
+            multiplier: snapshot.multiplier,
+            ema: EmaState::restore(snapshot.ema),
+            atr: AtrState::restore(snapshot.atr),
+        }
+    }
+}
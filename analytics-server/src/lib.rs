@@ -0,0 +1,23 @@
+pub mod analytics {
+    tonic::include_proto!("analytics");
+}
+pub mod data {
+    tonic::include_proto!("data");
+}
+/// The Arrow Flight protocol, generated from `proto/Flight.proto`.
+pub mod arrow_flight {
+    tonic::include_proto!("arrow.flight.protocol");
+}
+
+pub mod cache;
+pub mod feed;
+pub mod flight;
+pub mod indicators;
+pub mod live;
+pub mod metrics;
+pub mod paging;
+pub mod planner;
+pub mod query;
+pub mod registry;
+pub mod service;
+pub mod store;
@@ -0,0 +1,180 @@
+//! Keeps an indicator current from a live trade feed. Trades are folded into
+//! candles, and each candle that closes advances the indicator's streaming
+//! state. Indicators without one are recomputed over a bounded window of
+//! closed candles instead.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::analytics::{IndicatorUpdate, SeriesType};
+use crate::data;
+use crate::registry::{Indicator, IndicatorState, Inputs, Params};
+use crate::service::series_value;
+use crate::store::{Candle, CandleBuilder};
+
+/// Closed candles to seed a subscription with when the warm-up needs fewer,
+/// so recursive indicators like EMA start from a converged value.
+pub const HISTORY_CANDLES: usize = 500;
+
+/// How closed candles turn into values.
+enum Engine {
+    /// Advanced one closed candle at a time.
+    Streaming {
+        state: Box<dyn IndicatorState>,
+        /// Row at the last closed candle.
+        latest: Option<IndicatorUpdate>,
+    },
+    /// Recomputed over the last `capacity` closed candles, kept contiguous.
+    Window {
+        capacity: usize,
+        closed: VecDeque<Candle>,
+    },
+}
+
+/// One indicator over one symbol's candles, seeded from history and advanced
+/// by live trades.
+pub struct LiveIndicator {
+    indicator: Arc<dyn Indicator>,
+    params: Params,
+    series_type: SeriesType,
+    engine: Engine,
+    builder: CandleBuilder,
+    seeded_until: u64,
+}
+
+impl LiveIndicator {
+    /// `history` holds every candle up to `seeded_until`; the last one stays
+    /// open if its bucket has not ended by then. Live trades at or before
+    /// `seeded_until` are assumed to be in `history` already and are skipped.
+    pub fn new(
+        indicator: Arc<dyn Indicator>,
+        params: Params,
+        series_type: SeriesType,
+        interval: Duration,
+        mut history: Vec<Candle>,
+        seeded_until: u64,
+    ) -> Self {
+        let interval_micros = interval.as_micros() as u64;
+        let open = match history.last() {
+            Some(candle) if candle.timestamp + interval_micros > seeded_until => history.pop(),
+            _ => None,
+        };
+        let engine = match indicator.streaming(&params) {
+            Some(state) => Engine::Streaming {
+                state,
+                latest: None,
+            },
+            None => {
+                let capacity = (indicator.warm_up(&params) + 1).max(HISTORY_CANDLES);
+                let skip = history.len().saturating_sub(capacity);
+                history.drain(..skip);
+                Engine::Window {
+                    capacity,
+                    closed: VecDeque::with_capacity(capacity),
+                }
+            }
+        };
+        let mut live = Self {
+            indicator,
+            params,
+            series_type,
+            engine,
+            builder: CandleBuilder::new(interval, open),
+            seeded_until,
+        };
+        for candle in history {
+            live.close(candle);
+        }
+        live
+    }
+
+    /// Value at the last closed candle.
+    pub fn latest(&self) -> Option<IndicatorUpdate> {
+        match &self.engine {
+            Engine::Streaming { latest, .. } => latest.clone(),
+            Engine::Window { closed, .. } => self.evaluate(closed.as_slices().0, false),
+        }
+    }
+
+    /// Value including the candle still open, which may change before it
+    /// closes. A streaming state is forked so the open candle is applied
+    /// without being committed.
+    pub fn provisional(&self) -> Option<IndicatorUpdate> {
+        let open = self.builder.current()?;
+        match &self.engine {
+            Engine::Streaming { state, .. } => {
+                let value = series_value(open, self.series_type);
+                let values = state.fork().update(value, Some(open))?;
+                Some(IndicatorUpdate {
+                    timestamp: open.timestamp,
+                    values,
+                    provisional: true,
+                })
+            }
+            Engine::Window { closed, .. } => {
+                let mut candles: Vec<Candle> = closed.iter().cloned().collect();
+                candles.push(open.clone());
+                self.evaluate(&candles, true)
+            }
+        }
+    }
+
+    /// Adds a live trade and returns the new value if it closed a candle.
+    pub fn push(&mut self, trade: &data::Trade) -> Option<IndicatorUpdate> {
+        if trade.exchange_timestamp <= self.seeded_until {
+            return None;
+        }
+        let closed = self.builder.push(
+            trade.exchange_timestamp,
+            trade.price,
+            trade.quantity,
+            trade.taker_side(),
+        )?;
+        self.close(closed);
+        self.latest()
+    }
+
+    fn close(&mut self, candle: Candle) {
+        match &mut self.engine {
+            Engine::Streaming { state, latest } => {
+                let value = series_value(&candle, self.series_type);
+                if let Some(values) = state.update(value, Some(&candle)) {
+                    *latest = Some(IndicatorUpdate {
+                        timestamp: candle.timestamp,
+                        values,
+                        provisional: false,
+                    });
+                }
+            }
+            Engine::Window { capacity, closed } => {
+                if closed.len() == *capacity {
+                    closed.pop_front();
+                }
+                closed.push_back(candle);
+                closed.make_contiguous();
+            }
+        }
+    }
+
+    fn evaluate(&self, candles: &[Candle], provisional: bool) -> Option<IndicatorUpdate> {
+        let last = candles.last()?;
+        if candles.len() <= self.indicator.warm_up(&self.params) {
+            return None;
+        }
+        let values: Vec<f64> = candles
+            .iter()
+            .map(|candle| series_value(candle, self.series_type))
+            .collect();
+        let inputs = Inputs {
+            values: &values,
+            candles,
+        };
+        let values = self.indicator.compute(&inputs, &self.params).pop()?;
+        Some(IndicatorUpdate {
+            timestamp: last.timestamp,
+            values,
+            provisional,
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use prometheus::{Encoder, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+pub struct ServerMetrics {
+    registry: Registry,
+    /// Labelled by `rpc` and `result`: `hit`, `miss` or `bypass` (the window
+    /// was still open).
+    pub cache_requests: IntCounterVec,
+    pub cache_evictions: IntCounter,
+    /// Results larger than the per-entry limit, returned without caching.
+    pub cache_oversized: IntCounter,
+    pub cache_entries: IntGauge,
+    pub cache_bytes: IntGauge,
+}
+
+impl ServerMetrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("analytics_server".to_string()), None)?;
+
+        let cache_requests = IntCounterVec::new(
+            Opts::new(
+                "cache_requests_total",
+                "Result cache lookups by RPC and result",
+            ),
+            &["rpc", "result"],
+        )?;
+        let cache_evictions = IntCounter::new(
+            "cache_evictions_total",
+            "Results evicted as least recently used",
+        )?;
+        let cache_oversized =
+            IntCounter::new("cache_oversized_total", "Results too large to cache")?;
+        let cache_entries = IntGauge::new("cache_entries", "Results currently cached")?;
+        let cache_bytes = IntGauge::new("cache_bytes", "Encoded size of the cached results")?;
+
+        registry.register(Box::new(cache_requests.clone()))?;
+        registry.register(Box::new(cache_evictions.clone()))?;
+        registry.register(Box::new(cache_oversized.clone()))?;
+        registry.register(Box::new(cache_entries.clone()))?;
+        registry.register(Box::new(cache_bytes.clone()))?;
+
+        Ok(Self {
+            registry,
+            cache_requests,
+            cache_evictions,
+            cache_oversized,
+            cache_entries,
+            cache_bytes,
+        })
+    }
+
+    /// Prometheus text exposition of every registered metric.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
@@ -0,0 +1,342 @@
+//! Limits on how many points an indicator RPC returns: LTTB downsampling for
+//! charts, page tokens for unary calls and chunking for the streaming ones.
+//! Pages are cut lazily, so only the points up to the end of the page asked
+//! for are computed. A page token holds the timestamp of the last point sent
+//! and a hash of the request, and is rejected by any other request.
+
+use std::iter::Peekable;
+
+use prost::Message;
+use tonic::Status;
+
+use crate::analytics::{
+    ComputeIndicatorRequest, GetAtrRequest, GetBollingerBandsRequest, GetKeltnerChannelsRequest,
+    GetMacdRequest, GetMovingAverageRequest,
+};
+
+/// Points per message when a streaming request leaves `page_size` unset.
+pub const DEFAULT_CHUNK_SIZE: usize = 10_000;
+
+/// A request whose results come in pages.
+pub trait Paged: Message + Clone {
+    fn page_size(&self) -> u32;
+    fn page_token(&self) -> &str;
+    fn max_points(&self) -> u32;
+
+    /// Bytes identifying every field but the page size and token, which may
+    /// change from one page to the next.
+    fn identity(&self) -> Vec<u8>;
+}
+
+macro_rules! paged {
+    ($($request:ty),*) => {
+        $(impl Paged for $request {
+            fn page_size(&self) -> u32 {
+                self.page_size
+            }
+            fn page_token(&self) -> &str {
+                &self.page_token
+            }
+            fn max_points(&self) -> u32 {
+                self.max_points
+            }
+            fn identity(&self) -> Vec<u8> {
+                Self {
+                    page_size: 0,
+                    page_token: String::new(),
+                    ..self.clone()
+                }
+                .encode_to_vec()
+            }
+        })*
+    };
+}
+
+paged!(
+    GetMovingAverageRequest,
+    GetMacdRequest,
+    GetBollingerBandsRequest,
+    GetAtrRequest,
+    GetKeltnerChannelsRequest
+);
+
+impl Paged for ComputeIndicatorRequest {
+    fn page_size(&self) -> u32 {
+        self.page_size
+    }
+    fn page_token(&self) -> &str {
+        &self.page_token
+    }
+    fn max_points(&self) -> u32 {
+        self.max_points
+    }
+    /// Map fields encode in hash order, so `params` is appended sorted.
+    fn identity(&self) -> Vec<u8> {
+        let mut identity = Self {
+            params: Default::default(),
+            page_size: 0,
+            page_token: String::new(),
+            ..self.clone()
+        }
+        .encode_to_vec();
+        let mut params: Vec<(&String, &f64)> = self.params.iter().collect();
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, value) in params {
+            identity.extend_from_slice(name.as_bytes());
+            identity.push(0);
+            identity.extend_from_slice(&value.to_le_bytes());
+        }
+        identity
+    }
+}
+
+/// Where the previous page ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    /// Timestamp of the last point sent.
+    pub timestamp: u64,
+    /// Points sent with that timestamp, as trades can share one.
+    pub count: usize,
+}
+
+/// Paging fields of a request, validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paging {
+    /// Points per page; zero means no limit.
+    pub page_size: usize,
+    /// End of the previous page, decoded from the page token.
+    pub after: Option<Cursor>,
+    /// Downsample to at most this many points first; zero keeps them all.
+    pub max_points: usize,
+    /// Hash of the RPC and request identity, carried in every token.
+    fingerprint: u64,
+}
+
+impl Paging {
+    /// Reads the paging fields of `request`, a call to `rpc`, rejecting
+    /// tokens issued for any other call.
+    pub fn from_request(rpc: &str, request: &impl Paged) -> Result<Self, Status> {
+        let mut identity = rpc.as_bytes().to_vec();
+        identity.push(0);
+        identity.extend(request.identity());
+        let fingerprint = fnv1a(&identity);
+        let after = match request.page_token() {
+            "" => None,
+            token => Some(Self::parse(token, fingerprint)?),
+        };
+        Ok(Self {
+            page_size: request.page_size() as usize,
+            after,
+            max_points: request.max_points() as usize,
+            fingerprint,
+        })
+    }
+
+    fn parse(token: &str, fingerprint: u64) -> Result<Cursor, Status> {
+        let invalid = || Status::invalid_argument("invalid page_token");
+        let mut parts = token.split('-');
+        let (Some(hash), Some(timestamp), Some(count), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid())?;
+        let cursor = Cursor {
+            timestamp: timestamp.parse().map_err(|_| invalid())?,
+            count: count.parse().map_err(|_| invalid())?,
+        };
+        if hash != fingerprint || cursor.count == 0 {
+            return Err(Status::invalid_argument(
+                "page_token was issued for a different request",
+            ));
+        }
+        Ok(cursor)
+    }
+
+    /// The page of `points` this request asks for, and the token of the next
+    /// one (empty on the last page). `key` gives the timestamp and value LTTB
+    /// compares. Points past the page are never pulled.
+    pub fn page<T, K>(
+        &self,
+        points: impl IntoIterator<Item = T, IntoIter: Send + 'static>,
+        key: K,
+    ) -> (Vec<T>, String)
+    where
+        T: Send + 'static,
+        K: Fn(&T) -> (u64, f64),
+    {
+        let page_size = if self.page_size == 0 {
+            usize::MAX
+        } else {
+            self.page_size
+        };
+        self.pages(points, key, page_size)
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Every page from the token on, each with the token of the page after
+    /// it, cut as the iterator is advanced. Pages default to
+    /// [`DEFAULT_CHUNK_SIZE`] points.
+    pub fn chunks<T, K>(
+        &self,
+        points: impl IntoIterator<Item = T, IntoIter: Send + 'static>,
+        key: K,
+    ) -> Pages<T, K>
+    where
+        T: Send + 'static,
+        K: Fn(&T) -> (u64, f64),
+    {
+        let page_size = if self.page_size == 0 {
+            DEFAULT_CHUNK_SIZE
+        } else {
+            self.page_size
+        };
+        self.pages(points, key, page_size)
+    }
+
+    fn pages<T, K>(
+        &self,
+        points: impl IntoIterator<Item = T, IntoIter: Send + 'static>,
+        key: K,
+        page_size: usize,
+    ) -> Pages<T, K>
+    where
+        T: Send + 'static,
+        K: Fn(&T) -> (u64, f64),
+    {
+        let points: Box<dyn Iterator<Item = T> + Send> = if self.max_points > 0 {
+            Box::new(downsample(points.into_iter().collect(), &key, self.max_points).into_iter())
+        } else {
+            Box::new(points.into_iter())
+        };
+        let mut points = points.peekable();
+        if let Some(cursor) = self.after {
+            let mut skipped = 0;
+            while let Some(point) = points.peek() {
+                let timestamp = key(point).0;
+                if timestamp > cursor.timestamp
+                    || (timestamp == cursor.timestamp && skipped == cursor.count)
+                {
+                    break;
+                }
+                if timestamp == cursor.timestamp {
+                    skipped += 1;
+                }
+                points.next();
+            }
+        }
+        Pages {
+            points,
+            key,
+            page_size,
+            fingerprint: self.fingerprint,
+            cursor: self.after,
+        }
+    }
+}
+
+/// Pages cut lazily from a series of points, each with the token of the page
+/// after it.
+pub struct Pages<T, K> {
+    points: Peekable<Box<dyn Iterator<Item = T> + Send>>,
+    key: K,
+    page_size: usize,
+    fingerprint: u64,
+    cursor: Option<Cursor>,
+}
+
+impl<T, K: Fn(&T) -> (u64, f64)> Iterator for Pages<T, K> {
+    type Item = (Vec<T>, String);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page: Vec<T> = self.points.by_ref().take(self.page_size).collect();
+        let timestamp = (self.key)(page.last()?).0;
+        let at_end = page
+            .iter()
+            .rev()
+            .take_while(|point| (self.key)(point).0 == timestamp)
+            .count();
+        let count = match self.cursor {
+            Some(cursor) if cursor.timestamp == timestamp && at_end == page.len() => {
+                cursor.count + at_end
+            }
+            _ => at_end,
+        };
+        self.cursor = Some(Cursor { timestamp, count });
+        let next_page_token = if self.points.peek().is_some() {
+            format!("{:016x}-{}-{}", self.fingerprint, timestamp, count)
+        } else {
+            String::new()
+        };
+        Some((page, next_page_token))
+    }
+}
+
+/// At most `max_points` of `points`, picked by [`lttb`] over `key`.
+fn downsample<T>(points: Vec<T>, key: impl Fn(&T) -> (u64, f64), max_points: usize) -> Vec<T> {
+    if points.len() <= max_points {
+        return points;
+    }
+    let keys: Vec<(f64, f64)> = points
+        .iter()
+        .map(|point| {
+            let (timestamp, value) = key(point);
+            (timestamp as f64, value)
+        })
+        .collect();
+    let mut keep = lttb(&keys, max_points).into_iter().peekable();
+    points
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep.next_if_eq(i).is_some())
+        .map(|(_, point)| point)
+        .collect()
+}
+
+/// 64-bit FNV-1a, stable across builds so tokens outlive a restart.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Largest-Triangle-Three-Buckets: indices of at most `threshold` points of
+/// `points` (x, y) that keep the visual shape of the line. The first and last
+/// points are always kept.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<usize> {
+    let n = points.len();
+    if threshold >= n || threshold == 0 {
+        return (0..n).collect();
+    }
+    if threshold < 3 {
+        return [0, n - 1].into_iter().take(threshold).collect();
+    }
+
+    let every = (n - 2) as f64 / (threshold - 2) as f64;
+    let mut sampled = Vec::with_capacity(threshold);
+    let mut a = 0;
+    sampled.push(a);
+    for i in 0..threshold - 2 {
+        let next_start = ((i + 1) as f64 * every) as usize + 1;
+        let next_end = (((i + 2) as f64 * every) as usize + 1).min(n);
+        let next = &points[next_start..next_end];
+        let avg_x = next.iter().map(|p| p.0).sum::<f64>() / next.len() as f64;
+        let avg_y = next.iter().map(|p| p.1).sum::<f64>() / next.len() as f64;
+
+        let start = (i as f64 * every) as usize + 1;
+        let end = next_start;
+        let (ax, ay) = points[a];
+        let mut max_area = -1.0;
+        for (j, (x, y)) in points.iter().enumerate().take(end).skip(start) {
+            let area = ((ax - avg_x) * (y - ay) - (ax - x) * (avg_y - ay)).abs();
+            if area > max_area {
+                max_area = area;
+                a = j;
+            }
+        }
+        sampled.push(a);
+    }
+    sampled.push(n - 1);
+    sampled
+}
@@ -0,0 +1,221 @@
+//! Decides where trade aggregations run. A store that can aggregate where the
+//! trades live, like ClickHouse, is sent the aggregation and returns a handful
+//! of rows; otherwise the aggregation runs in process as DataFusion SQL over
+//! the `trades` table, which still pushes the symbol and time range down to
+//! the store. Indicators always run in process, over the candles.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use datafusion::arrow::array::{AsArray, RecordBatch};
+use datafusion::arrow::compute::{cast, concat_batches};
+use datafusion::arrow::datatypes::{ArrowPrimitiveType, Float64Type, UInt64Type};
+use datafusion::prelude::SessionContext;
+use datafusion::scalar::ScalarValue;
+use tonic::Status;
+
+use crate::store::table::{CandleTable, StoreTable};
+use crate::store::{Aggregation, Candle, TimeRange, TradeStore, TradeSummary};
+
+/// Width of the candles in the `candles` table.
+pub const CANDLES_TABLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where an aggregation runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plan {
+    /// In the store, e.g. as ClickHouse SQL.
+    Pushdown,
+    /// Over the raw trades, with DataFusion.
+    InProcess,
+}
+
+/// Runs aggregations in the store or over `trades`, registered once in a
+/// long-lived DataFusion context from [`TradeStore::table`] next to
+/// one-minute `candles`.
+pub struct Planner {
+    store: Arc<dyn TradeStore>,
+    forced: Option<Plan>,
+    ctx: SessionContext,
+}
+
+impl Planner {
+    pub fn new(store: Arc<dyn TradeStore>) -> Self {
+        Self {
+            ctx: context(&store),
+            store,
+            forced: None,
+        }
+    }
+
+    /// Runs every aggregation with `plan`, to compare the two. Forcing
+    /// [`Plan::Pushdown`] on a store that does not push down fails with
+    /// `unimplemented`.
+    pub fn forced(store: Arc<dyn TradeStore>, plan: Plan) -> Self {
+        Self {
+            forced: Some(plan),
+            ..Self::new(store)
+        }
+    }
+
+    /// The DataFusion context with `trades` and `candles` registered.
+    pub fn context(&self) -> &SessionContext {
+        &self.ctx
+    }
+
+    pub fn plan(&self, aggregation: Aggregation) -> Plan {
+        match self.forced {
+            Some(plan) => plan,
+            None if self.store.pushes_down(aggregation) => Plan::Pushdown,
+            None => Plan::InProcess,
+        }
+    }
+
+    pub async fn summary(&self, symbol: &str, range: TimeRange) -> Result<TradeSummary, Status> {
+        match self.plan(Aggregation::Summary) {
+            Plan::Pushdown => self.store.summary(symbol, range).await,
+            Plan::InProcess => {
+                let batch = self.query(SUMMARY, symbol, range).await?;
+                let trade_count = column::<UInt64Type>(&batch, "trade_count")?[0];
+                if trade_count == 0 {
+                    return Ok(TradeSummary::default());
+                }
+                Ok(TradeSummary {
+                    volume: column::<Float64Type>(&batch, "volume")?[0],
+                    quote_volume: column::<Float64Type>(&batch, "quote_volume")?[0],
+                    trade_count,
+                })
+            }
+        }
+    }
+
+    /// Candles of width `interval` aligned to the epoch; buckets without trades
+    /// are omitted.
+    pub async fn candles(
+        &self,
+        symbol: &str,
+        range: TimeRange,
+        interval: Duration,
+    ) -> Result<Vec<Candle>, Status> {
+        match self.plan(Aggregation::Candles) {
+            Plan::Pushdown => self.store.candles(symbol, range, interval).await,
+            Plan::InProcess => {
+                let interval_micros = interval.as_micros() as u64;
+                if interval_micros == 0 {
+                    return Err(Status::invalid_argument("interval must be positive"));
+                }
+                let sql = CANDLES.replace("{interval}", &interval_micros.to_string());
+                let batch = self.query(&sql, symbol, range).await?;
+                candles(&batch)
+            }
+        }
+    }
+
+    /// Runs `sql` with `symbol` and the bounds of `range` as `$1`, `$2` and
+    /// `$3`.
+    async fn query(
+        &self,
+        sql: &str,
+        symbol: &str,
+        range: TimeRange,
+    ) -> Result<RecordBatch, Status> {
+        let bound = |micros: u64| ScalarValue::from(i64::try_from(micros).unwrap_or(i64::MAX));
+        let df = self
+            .ctx
+            .sql(sql)
+            .await
+            .and_then(|df| {
+                df.with_param_values(vec![
+                    ScalarValue::from(symbol),
+                    bound(range.start_micros),
+                    bound(range.end_micros),
+                ])
+            })
+            .map_err(|e| Status::internal(format!("Error executing query: {}", e)))?;
+        let schema = df.schema().inner().clone();
+        let batches = df
+            .collect()
+            .await
+            .map_err(|e| Status::internal(format!("Error collecting results: {}", e)))?;
+        concat_batches(&schema, &batches)
+            .map_err(|e| Status::internal(format!("Error collecting results: {}", e)))
+    }
+}
+
+fn context(store: &Arc<dyn TradeStore>) -> SessionContext {
+    let ctx = SessionContext::new();
+    let trades = store
+        .table()
+        .unwrap_or_else(|| Arc::new(StoreTable::new(store.clone())));
+    let candles = Arc::new(CandleTable::new(store.clone(), CANDLES_TABLE_INTERVAL));
+    ctx.register_table("trades", trades)
+        .expect("`trades` is only registered once");
+    ctx.register_table("candles", candles)
+        .expect("`candles` is only registered once");
+    ctx
+}
+
+fn candles(batch: &RecordBatch) -> Result<Vec<Candle>, Status> {
+    let timestamps = column::<UInt64Type>(batch, "bucket")?;
+    let open = column::<Float64Type>(batch, "open")?;
+    let high = column::<Float64Type>(batch, "high")?;
+    let low = column::<Float64Type>(batch, "low")?;
+    let close = column::<Float64Type>(batch, "close")?;
+    let volume = column::<Float64Type>(batch, "volume")?;
+    let quote_volume = column::<Float64Type>(batch, "quote_volume")?;
+    let trade_count = column::<UInt64Type>(batch, "trade_count")?;
+    let buy_volume = column::<Float64Type>(batch, "buy_volume")?;
+    let sell_volume = column::<Float64Type>(batch, "sell_volume")?;
+    Ok((0..batch.num_rows())
+        .map(|i| Candle {
+            timestamp: timestamps[i],
+            open: open[i],
+            high: high[i],
+            low: low[i],
+            close: close[i],
+            volume: volume[i],
+            quote_volume: quote_volume[i],
+            trade_count: trade_count[i],
+            buy_volume: buy_volume[i],
+            sell_volume: sell_volume[i],
+        })
+        .collect())
+}
+
+const SUMMARY: &str = "SELECT
+        SUM(quantity) AS volume,
+        SUM(price * quantity) AS quote_volume,
+        COUNT(*) AS trade_count
+     FROM trades
+     WHERE symbol = $1 AND exchange_timestamp >= $2 AND exchange_timestamp <= $3";
+
+/// `{interval}` is replaced by the bucket width in microseconds. Trades
+/// sharing a timestamp may open or close a candle in either order, as in
+/// ClickHouse.
+const CANDLES: &str = "SELECT
+        exchange_timestamp / {interval} * {interval} AS bucket,
+        first_value(price ORDER BY exchange_timestamp) AS open,
+        MAX(price) AS high,
+        MIN(price) AS low,
+        last_value(price ORDER BY exchange_timestamp) AS close,
+        SUM(quantity) AS volume,
+        SUM(price * quantity) AS quote_volume,
+        COUNT(*) AS trade_count,
+        SUM(CASE WHEN taker_side = 'BUY' THEN quantity ELSE 0 END) AS buy_volume,
+        SUM(CASE WHEN taker_side = 'SELL' THEN quantity ELSE 0 END) AS sell_volume
+     FROM trades
+     WHERE symbol = $1 AND exchange_timestamp >= $2 AND exchange_timestamp <= $3
+     GROUP BY bucket
+     ORDER BY bucket";
+
+/// The values of the `name` column, cast to `T`.
+fn column<T: ArrowPrimitiveType>(
+    batch: &RecordBatch,
+    name: &str,
+) -> Result<Vec<T::Native>, Status> {
+    let column = batch
+        .column_by_name(name)
+        .ok_or_else(|| Status::internal(format!("Query result has no `{name}` column")))?;
+    let column = cast(column, &T::DATA_TYPE)
+        .map_err(|e| Status::internal(format!("Error reading `{name}`: {}", e)))?;
+    Ok(column.as_primitive::<T>().values().to_vec())
+}
@@ -0,0 +1,279 @@
+//! Ad-hoc, read-only SQL over `trades` and `candles`. Each query runs in its
+//! own DataFusion context sharing the planner's tables, with a memory pool,
+//! a row cap and a deadline, and may only call allowlisted functions.
+//! Dropping the future, as tonic does when the client cancels the call,
+//! stops the query.
+
+use std::time::Duration;
+
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::writer::StreamWriter;
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::error::DataFusionError;
+use datafusion::execution::context::SQLOptions;
+use datafusion::execution::runtime_env::RuntimeEnvBuilder;
+use datafusion::logical_expr::{Expr, LogicalPlan};
+use datafusion::prelude::{DataFrame, SessionConfig, SessionContext};
+use futures::StreamExt;
+use tonic::Status;
+
+/// Tables a query may read.
+pub const TABLES: [&str; 2] = ["trades", "candles"];
+
+/// Functions a query may call: aggregates, window functions and scalar
+/// functions without side effects.
+pub const FUNCTIONS: &[&str] = &[
+    // Aggregates.
+    "count",
+    "sum",
+    "avg",
+    "min",
+    "max",
+    "median",
+    "stddev",
+    "stddev_pop",
+    "var",
+    "var_pop",
+    "corr",
+    "covar_samp",
+    "covar_pop",
+    "regr_slope",
+    "regr_intercept",
+    "regr_r2",
+    "approx_distinct",
+    "approx_median",
+    "approx_percentile_cont",
+    "bool_and",
+    "bool_or",
+    // Window functions.
+    "row_number",
+    "rank",
+    "dense_rank",
+    "percent_rank",
+    "cume_dist",
+    "ntile",
+    "lag",
+    "lead",
+    "first_value",
+    "last_value",
+    "nth_value",
+    // Scalar functions.
+    "abs",
+    "ceil",
+    "floor",
+    "round",
+    "trunc",
+    "signum",
+    "sqrt",
+    "cbrt",
+    "exp",
+    "ln",
+    "log",
+    "log2",
+    "log10",
+    "power",
+    "greatest",
+    "least",
+    "coalesce",
+    "nullif",
+    "nvl",
+    "isnan",
+    "date_trunc",
+    "date_bin",
+    "date_part",
+    "to_timestamp",
+    "to_timestamp_seconds",
+    "to_timestamp_millis",
+    "to_timestamp_micros",
+    "from_unixtime",
+    "lower",
+    "upper",
+    "concat",
+    "substr",
+    "starts_with",
+    "character_length",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Rows returned at most; the rest are cut off.
+    pub max_rows: usize,
+    pub timeout: Duration,
+    /// Bytes DataFusion may hold for sorts, joins and aggregations.
+    pub max_memory: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        Self {
+            max_rows: 100_000,
+            timeout: Duration::from_secs(30),
+            max_memory: 512 * 1024 * 1024,
+        }
+    }
+}
+
+/// A query's result as an Arrow IPC stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult {
+    pub arrow_ipc: Vec<u8>,
+    pub rows: usize,
+    /// Whether rows past `max_rows` were cut off.
+    pub truncated: bool,
+}
+
+/// A query's result as record batches.
+#[derive(Debug, Clone)]
+pub struct QueryBatches {
+    pub schema: SchemaRef,
+    pub batches: Vec<RecordBatch>,
+    pub rows: usize,
+    /// Whether rows past `max_rows` were cut off.
+    pub truncated: bool,
+}
+
+/// Runs `sql` over the tables registered in `tables`, encoding the result as
+/// an Arrow IPC stream.
+pub async fn execute(
+    tables: &SessionContext,
+    sql: &str,
+    limits: QueryLimits,
+) -> Result<QueryResult, Status> {
+    let result = run(tables, sql, limits).await?;
+    let mut writer = StreamWriter::try_new(Vec::new(), &result.schema).map_err(internal)?;
+    for batch in &result.batches {
+        writer.write(batch).map_err(internal)?;
+    }
+    writer.finish().map_err(internal)?;
+    Ok(QueryResult {
+        arrow_ipc: writer.into_inner().map_err(internal)?,
+        rows: result.rows,
+        truncated: result.truncated,
+    })
+}
+
+/// Runs `sql` over the tables registered in `tables`.
+pub async fn run(
+    tables: &SessionContext,
+    sql: &str,
+    limits: QueryLimits,
+) -> Result<QueryBatches, Status> {
+    let df = plan(tables, sql, limits).await?;
+    // One extra row tells a result that fits from one that was cut off.
+    let df = df
+        .limit(0, Some(limits.max_rows.saturating_add(1)))
+        .map_err(internal)?;
+    tokio::time::timeout(limits.timeout, collect(df, limits.max_rows))
+        .await
+        .map_err(|_| {
+            Status::deadline_exceeded(format!("Query ran longer than {:?}", limits.timeout))
+        })?
+}
+
+/// The schema of `sql`'s result, checked as [`run`] would without running it.
+pub async fn schema(tables: &SessionContext, sql: &str) -> Result<SchemaRef, Status> {
+    let df = plan(tables, sql, QueryLimits::default()).await?;
+    Ok(df.schema().inner().clone())
+}
+
+async fn plan(
+    tables: &SessionContext,
+    sql: &str,
+    limits: QueryLimits,
+) -> Result<DataFrame, Status> {
+    let runtime = RuntimeEnvBuilder::new()
+        .with_memory_limit(limits.max_memory, 1.0)
+        .build_arc()
+        .map_err(internal)?;
+    let ctx = SessionContext::new_with_config_rt(SessionConfig::new(), runtime);
+    for name in TABLES {
+        let table = tables.table_provider(name).await.map_err(internal)?;
+        ctx.register_table(name, table).map_err(internal)?;
+    }
+
+    let options = SQLOptions::new()
+        .with_allow_ddl(false)
+        .with_allow_dml(false)
+        .with_allow_statements(false);
+    let df = ctx
+        .sql_with_options(sql, options)
+        .await
+        .map_err(|e| Status::invalid_argument(format!("Invalid query: {}", e)))?;
+    check(df.logical_plan())?;
+    Ok(df)
+}
+
+/// Rejects plans that read other tables or call functions outside
+/// [`FUNCTIONS`].
+fn check(plan: &LogicalPlan) -> Result<(), Status> {
+    let mut denied = None;
+    plan.apply_with_subqueries(|node| {
+        match node {
+            LogicalPlan::TableScan(scan) if !TABLES.contains(&scan.table_name.table()) => {
+                denied = Some(format!("table `{}`", scan.table_name));
+                return Ok(TreeNodeRecursion::Stop);
+            }
+            _ => {}
+        }
+        node.apply_expressions(|expr| {
+            expr.apply(|expr| {
+                let name = match expr {
+                    Expr::ScalarFunction(function) => function.name(),
+                    Expr::AggregateFunction(function) => function.func.name(),
+                    Expr::WindowFunction(function) => function.fun.name(),
+                    _ => return Ok(TreeNodeRecursion::Continue),
+                };
+                if FUNCTIONS.contains(&name) {
+                    return Ok(TreeNodeRecursion::Continue);
+                }
+                denied = Some(format!("function `{name}`"));
+                Ok(TreeNodeRecursion::Stop)
+            })
+        })
+    })
+    .map_err(internal)?;
+    match denied {
+        Some(denied) => Err(Status::permission_denied(format!(
+            "Queries may not use {denied}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+async fn collect(df: DataFrame, max_rows: usize) -> Result<QueryBatches, Status> {
+    let mut stream = df.execute_stream().await.map_err(execution)?;
+    let schema = stream.schema();
+    let mut batches = Vec::new();
+    let mut rows = 0;
+    let mut truncated = false;
+    while let Some(batch) = stream.next().await {
+        let batch = batch.map_err(execution)?;
+        let take = batch.num_rows().min(max_rows - rows);
+        batches.push(batch.slice(0, take));
+        rows += take;
+        if take < batch.num_rows() {
+            truncated = true;
+            break;
+        }
+    }
+    Ok(QueryBatches {
+        schema,
+        batches,
+        rows,
+        truncated,
+    })
+}
+
+fn execution(error: DataFusionError) -> Status {
+    match error.find_root() {
+        DataFusionError::ResourcesExhausted(_) => {
+            Status::resource_exhausted(format!("Query ran out of memory: {}", error))
+        }
+        _ => Status::internal(format!("Error executing query: {}", error)),
+    }
+}
+
+fn internal(error: impl std::fmt::Display) -> Status {
+    Status::internal(format!("Error executing query: {}", error))
+}
@@ -0,0 +1,566 @@
+//! Named indicators behind one interface, so `ComputeIndicator` can serve any
+//! of them and `ListIndicators` can describe them. Adding an indicator means
+//! implementing [`Indicator`] over the math in [`crate::indicators`] and
+//! listing it in [`Registry::builtin`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tonic::Status;
+
+use crate::indicators::streaming::{
+    AtrState, BollingerState, EmaState, Hlc, KeltnerState, MacdState, RsiState, SmaState, Streaming,
+};
+use crate::indicators::{self, Band, MacdPeriods};
+use crate::store::Candle;
+
+/// A tunable input of an indicator.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether the value must be a whole number, like a window length.
+    pub integer: bool,
+    pub default: f64,
+    /// Smallest accepted value, inclusive.
+    pub min: f64,
+}
+
+/// What an indicator runs over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// One value per candle (picked by `series_type`), or trade prices when
+    /// no interval is given.
+    Series,
+    /// Whole candles, so an interval is required.
+    Candles,
+}
+
+/// Parameter values with every default filled in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params(HashMap<String, f64>);
+
+impl Params {
+    /// Checks `values` against `specs`, rejecting unknown names, fractional
+    /// integers and values below the minimum.
+    pub fn resolve(specs: &[ParamSpec], values: &HashMap<String, f64>) -> Result<Self, Status> {
+        if let Some(name) = values
+            .keys()
+            .find(|name| !specs.iter().any(|spec| spec.name == name.as_str()))
+        {
+            return Err(Status::invalid_argument(format!(
+                "unknown parameter `{name}`"
+            )));
+        }
+        let mut params = HashMap::new();
+        for spec in specs {
+            let value = values.get(spec.name).copied().unwrap_or(spec.default);
+            if !value.is_finite() || (spec.integer && value.fract() != 0.0) {
+                return Err(Status::invalid_argument(format!(
+                    "`{}` must be a{} number",
+                    spec.name,
+                    if spec.integer { " whole" } else { " finite" }
+                )));
+            }
+            if value < spec.min {
+                return Err(Status::invalid_argument(format!(
+                    "`{}` must be at least {}",
+                    spec.name, spec.min
+                )));
+            }
+            params.insert(spec.name.to_string(), value);
+        }
+        Ok(Self(params))
+    }
+
+    pub fn f64(&self, name: &str) -> f64 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn usize(&self, name: &str) -> usize {
+        self.f64(name) as usize
+    }
+}
+
+/// Values an indicator reads. `candles` is empty when the series comes from
+/// trades.
+pub struct Inputs<'a> {
+    pub values: &'a [f64],
+    pub candles: &'a [Candle],
+}
+
+pub trait Indicator: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn params(&self) -> &'static [ParamSpec];
+    fn input(&self) -> Input;
+    /// Names of the values in each output row.
+    fn outputs(&self) -> &'static [&'static str];
+
+    /// Checks constraints between parameters.
+    fn validate(&self, _params: &Params) -> Result<(), Status> {
+        Ok(())
+    }
+
+    /// Index of the input the first output row belongs to.
+    fn warm_up(&self, params: &Params) -> usize;
+
+    /// One row per input from `warm_up` on, each with a value per output.
+    /// Callers guarantee more than `warm_up` inputs.
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>>;
+
+    /// The rows of [`compute`](Self::compute) one input at a time, for
+    /// indicators that can run incrementally. Live subscriptions advance it
+    /// per closed candle instead of recomputing.
+    fn streaming(&self, _params: &Params) -> Option<Box<dyn IndicatorState>> {
+        None
+    }
+}
+
+/// An indicator fed one input at a time, as [`Indicator::streaming`] returns.
+pub trait IndicatorState: Send {
+    /// Feeds the next input, returning its row once warmed up. `candle` is
+    /// `None` when the series comes from trades.
+    fn update(&mut self, value: f64, candle: Option<&Candle>) -> Option<Vec<f64>>;
+
+    /// An independent copy, to run ahead without advancing this state.
+    fn fork(&self) -> Box<dyn IndicatorState>;
+}
+
+/// A [`Streaming`] machine behind [`IndicatorState`].
+struct Machine<S: Streaming> {
+    state: S,
+    input: fn(f64, Option<&Candle>) -> Option<S::Input>,
+    output: fn(S::Output) -> Vec<f64>,
+}
+
+impl<S: Streaming + Send + 'static> IndicatorState for Machine<S> {
+    fn update(&mut self, value: f64, candle: Option<&Candle>) -> Option<Vec<f64>> {
+        let input = (self.input)(value, candle)?;
+        self.state.update(input).map(self.output)
+    }
+
+    fn fork(&self) -> Box<dyn IndicatorState> {
+        Box::new(Machine {
+            state: S::restore(self.state.snapshot()),
+            input: self.input,
+            output: self.output,
+        })
+    }
+}
+
+/// A machine over the series values.
+fn over_values<S>(state: S, output: fn(S::Output) -> Vec<f64>) -> Option<Box<dyn IndicatorState>>
+where
+    S: Streaming<Input = f64> + Send + 'static,
+{
+    Some(Box::new(Machine {
+        state,
+        input: |value, _| Some(value),
+        output,
+    }))
+}
+
+/// A machine over whole candles; it never advances without them.
+fn over_candles<S>(state: S, output: fn(S::Output) -> Vec<f64>) -> Option<Box<dyn IndicatorState>>
+where
+    S: Streaming<Input = Hlc> + Send + 'static,
+{
+    Some(Box::new(Machine {
+        state,
+        input: |_, candle| candle.map(Hlc::from),
+        output,
+    }))
+}
+
+struct Sma;
+
+impl Indicator for Sma {
+    fn name(&self) -> &'static str {
+        "sma"
+    }
+    fn description(&self) -> &'static str {
+        "Simple moving average"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[ParamSpec {
+            name: "window",
+            description: "Number of periods",
+            integer: true,
+            default: 20.0,
+            min: 1.0,
+        }];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Series
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["sma"]
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        params.usize("window") - 1
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        rows(indicators::sma(inputs.values, params.usize("window")))
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        over_values(SmaState::new(params.usize("window")), row)
+    }
+}
+
+struct Ema;
+
+impl Indicator for Ema {
+    fn name(&self) -> &'static str {
+        "ema"
+    }
+    fn description(&self) -> &'static str {
+        "Exponential moving average seeded with the SMA of the first window"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[ParamSpec {
+            name: "window",
+            description: "Number of periods",
+            integer: true,
+            default: 20.0,
+            min: 1.0,
+        }];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Series
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["ema"]
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        params.usize("window") - 1
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        rows(indicators::ema(inputs.values, params.usize("window")))
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        over_values(EmaState::new(params.usize("window")), row)
+    }
+}
+
+struct Rsi;
+
+impl Indicator for Rsi {
+    fn name(&self) -> &'static str {
+        "rsi"
+    }
+    fn description(&self) -> &'static str {
+        "Relative Strength Index with Wilder smoothing"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[ParamSpec {
+            name: "window",
+            description: "Number of changes",
+            integer: true,
+            default: 14.0,
+            min: 1.0,
+        }];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Series
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["rsi"]
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        params.usize("window")
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        rows(indicators::rsi(inputs.values, params.usize("window")))
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        over_values(RsiState::new(params.usize("window")), row)
+    }
+}
+
+struct Macd;
+
+impl Indicator for Macd {
+    fn name(&self) -> &'static str {
+        "macd"
+    }
+    fn description(&self) -> &'static str {
+        "Moving Average Convergence Divergence"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[
+            ParamSpec {
+                name: "fast_period",
+                description: "Period of the fast EMA",
+                integer: true,
+                default: 12.0,
+                min: 1.0,
+            },
+            ParamSpec {
+                name: "slow_period",
+                description: "Period of the slow EMA",
+                integer: true,
+                default: 26.0,
+                min: 1.0,
+            },
+            ParamSpec {
+                name: "signal_period",
+                description: "Period of the signal EMA over the MACD line",
+                integer: true,
+                default: 9.0,
+                min: 1.0,
+            },
+        ];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Series
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["macd", "signal", "histogram"]
+    }
+    fn validate(&self, params: &Params) -> Result<(), Status> {
+        macd_periods(params).map(|_| ())
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        macd_periods(params).map_or(0, |periods| periods.warm_up())
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        let Ok(periods) = macd_periods(params) else {
+            return Vec::new();
+        };
+        indicators::macd(inputs.values, periods)
+            .into_iter()
+            .map(|row| vec![row.macd, row.signal, row.histogram])
+            .collect()
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        let periods = macd_periods(params).ok()?;
+        over_values(MacdState::new(periods), |row| {
+            vec![row.macd, row.signal, row.histogram]
+        })
+    }
+}
+
+fn macd_periods(params: &Params) -> Result<MacdPeriods, Status> {
+    MacdPeriods::new(
+        params.usize("fast_period"),
+        params.usize("slow_period"),
+        params.usize("signal_period"),
+    )
+}
+
+struct Bollinger;
+
+impl Indicator for Bollinger {
+    fn name(&self) -> &'static str {
+        "bollinger"
+    }
+    fn description(&self) -> &'static str {
+        "SMA plus and minus a multiple of the population standard deviation"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[
+            ParamSpec {
+                name: "window",
+                description: "Number of periods",
+                integer: true,
+                default: 20.0,
+                min: 1.0,
+            },
+            ParamSpec {
+                name: "num_std_dev",
+                description: "Band width in standard deviations",
+                integer: false,
+                default: 2.0,
+                min: 0.0,
+            },
+        ];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Series
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["upper", "middle", "lower"]
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        params.usize("window") - 1
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        bands(indicators::bollinger_bands(
+            inputs.values,
+            params.usize("window"),
+            params.f64("num_std_dev"),
+        ))
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        over_values(
+            BollingerState::new(params.usize("window"), params.f64("num_std_dev")),
+            band,
+        )
+    }
+}
+
+struct Atr;
+
+impl Indicator for Atr {
+    fn name(&self) -> &'static str {
+        "atr"
+    }
+    fn description(&self) -> &'static str {
+        "Average True Range with Wilder smoothing"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[ParamSpec {
+            name: "window",
+            description: "Number of true ranges",
+            integer: true,
+            default: 14.0,
+            min: 1.0,
+        }];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Candles
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["atr"]
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        params.usize("window")
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        rows(indicators::atr(inputs.candles, params.usize("window")))
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        over_candles(AtrState::new(params.usize("window")), row)
+    }
+}
+
+struct Keltner;
+
+impl Indicator for Keltner {
+    fn name(&self) -> &'static str {
+        "keltner"
+    }
+    fn description(&self) -> &'static str {
+        "EMA of closes plus and minus a multiple of the ATR"
+    }
+    fn params(&self) -> &'static [ParamSpec] {
+        const PARAMS: &[ParamSpec] = &[
+            ParamSpec {
+                name: "window",
+                description: "Period of the middle EMA",
+                integer: true,
+                default: 20.0,
+                min: 1.0,
+            },
+            ParamSpec {
+                name: "atr_window",
+                description: "Period of the ATR",
+                integer: true,
+                default: 10.0,
+                min: 1.0,
+            },
+            ParamSpec {
+                name: "multiplier",
+                description: "Band width in ATRs",
+                integer: false,
+                default: 2.0,
+                min: 0.0,
+            },
+        ];
+        PARAMS
+    }
+    fn input(&self) -> Input {
+        Input::Candles
+    }
+    fn outputs(&self) -> &'static [&'static str] {
+        &["upper", "middle", "lower"]
+    }
+    fn warm_up(&self, params: &Params) -> usize {
+        indicators::keltner_warm_up(params.usize("window"), params.usize("atr_window"))
+    }
+    fn compute(&self, inputs: &Inputs, params: &Params) -> Vec<Vec<f64>> {
+        bands(indicators::keltner_channels(
+            inputs.candles,
+            params.usize("window"),
+            params.usize("atr_window"),
+            params.f64("multiplier"),
+        ))
+    }
+    fn streaming(&self, params: &Params) -> Option<Box<dyn IndicatorState>> {
+        over_candles(
+            KeltnerState::new(
+                params.usize("window"),
+                params.usize("atr_window"),
+                params.f64("multiplier"),
+            ),
+            band,
+        )
+    }
+}
+
+fn row(value: f64) -> Vec<f64> {
+    vec![value]
+}
+
+fn band(band: Band) -> Vec<f64> {
+    vec![band.upper, band.middle, band.lower]
+}
+
+fn rows(values: Vec<f64>) -> Vec<Vec<f64>> {
+    values.into_iter().map(row).collect()
+}
+
+fn bands(bands: Vec<Band>) -> Vec<Vec<f64>> {
+    bands.into_iter().map(band).collect()
+}
+
+/// Indicators served by `ComputeIndicator`, looked up by name.
+#[derive(Default)]
+pub struct Registry {
+    indicators: Vec<Arc<dyn Indicator>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every indicator this crate implements.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(Sma);
+        registry.register(Ema);
+        registry.register(Rsi);
+        registry.register(Macd);
+        registry.register(Bollinger);
+        registry.register(Atr);
+        registry.register(Keltner);
+        registry
+    }
+
+    /// Adds an indicator, replacing any with the same name.
+    pub fn register(&mut self, indicator: impl Indicator + 'static) {
+        self.indicators
+            .retain(|existing| !existing.name().eq_ignore_ascii_case(indicator.name()));
+        self.indicators.push(Arc::new(indicator));
+    }
+
+    /// Case-insensitive lookup.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Indicator>> {
+        self.indicators
+            .iter()
+            .find(|indicator| indicator.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Indicator> {
+        self.indicators.iter().map(|indicator| indicator.as_ref())
+    }
+}
@@ -0,0 +1,1150 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::StreamExt;
+use futures::stream::{self, BoxStream};
+use tonic::{Request, Response, Status};
+
+use crate::analytics::analytics_service_server::AnalyticsService;
+use crate::analytics::{
+    BandDataPoint, Candle, ComputeIndicatorRequest, ComputeIndicatorResponse, ExecuteQueryRequest,
+    ExecuteQueryResponse, GetAtrRequest, GetBandsResponse, GetBollingerBandsRequest,
+    GetCandlesRequest, GetCandlesResponse, GetKeltnerChannelsRequest, GetMacdRequest,
+    GetMacdResponse, GetMovingAverageRequest, GetMovingAverageResponse, GetTradeAnalyticsRequest,
+    GetTradeAnalyticsResponse, IndicatorDescriptor, IndicatorInput, IndicatorParameter,
+    IndicatorPoint, IndicatorUpdate, ListIndicatorsRequest, ListIndicatorsResponse, MacdDataPoint,
+    MovingAverageDataPoint, SeriesType, SubscribeToIndicatorRequest, SubscribeToTradesRequest,
+};
+use crate::cache::{CacheConfig, CacheKey, ResultCache};
+use crate::feed::{TradeFeed, TradeStream};
+use crate::indicators::streaming::{
+    AtrState, BollingerState, EmaState, Hlc, KeltnerState, RsiState, SmaState, Streaming,
+};
+use crate::indicators::{self, Band, MacdPeriods};
+use crate::live::{self, LiveIndicator};
+use crate::metrics::ServerMetrics;
+use crate::paging::Paging;
+use crate::planner::Planner;
+use crate::query::{self, QueryLimits};
+use crate::registry::{Indicator, Input, Inputs, Params, Registry};
+use crate::store::{self, TimeRange, TradeStore, Trades};
+
+/// Values an indicator runs over, each stamped with the trade or candle it
+/// came from.
+struct Series {
+    timestamps: Vec<u64>,
+    values: Vec<f64>,
+}
+
+/// Indicator points, computed as they are pulled.
+type Points<T> = Box<dyn Iterator<Item = T> + Send>;
+
+/// A `ComputeIndicator` result before paging.
+pub(crate) struct IndicatorPoints {
+    outputs: Vec<String>,
+    warm_up: u32,
+    points: Points<IndicatorPoint>,
+}
+
+pub(crate) fn series_value(candle: &store::Candle, series_type: SeriesType) -> f64 {
+    match series_type {
+        SeriesType::Close => candle.close,
+        SeriesType::Open => candle.open,
+        SeriesType::High => candle.high,
+        SeriesType::Low => candle.low,
+    }
+}
+
+pub struct AnalyticsServiceHandler {
+    store: Arc<dyn TradeStore>,
+    planner: Planner,
+    feed: Arc<dyn TradeFeed>,
+    registry: Arc<Registry>,
+    query_limits: QueryLimits,
+    metrics: Arc<ServerMetrics>,
+    cache: ResultCache,
+}
+
+impl AnalyticsServiceHandler {
+    pub fn new(store: Arc<dyn TradeStore>, feed: Arc<dyn TradeFeed>) -> Self {
+        let metrics = Arc::new(ServerMetrics::new().expect("metrics are registered once"));
+        Self {
+            planner: Planner::new(store.clone()),
+            store,
+            feed,
+            registry: Arc::new(Registry::builtin()),
+            query_limits: QueryLimits::default(),
+            cache: ResultCache::new(CacheConfig::default(), metrics.clone()),
+            metrics,
+        }
+    }
+
+    /// Limits applied to every `ExecuteQuery` call.
+    pub fn with_query_limits(mut self, query_limits: QueryLimits) -> Self {
+        self.query_limits = query_limits;
+        self
+    }
+
+    /// Caches `GetTradeAnalytics` and `GetMacd` results over closed windows.
+    pub fn with_cache(mut self, config: CacheConfig) -> Self {
+        self.cache = ResultCache::new(config, self.metrics.clone());
+        self
+    }
+
+    pub fn metrics(&self) -> Arc<ServerMetrics> {
+        self.metrics.clone()
+    }
+
+    pub(crate) fn planner(&self) -> &Planner {
+        &self.planner
+    }
+
+    pub(crate) fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub(crate) fn query_limits(&self) -> QueryLimits {
+        self.query_limits
+    }
+
+    async fn fetch_trades(
+        &self,
+        symbol: &str,
+        start: Option<&prost_types::Timestamp>,
+        end: Option<&prost_types::Timestamp>,
+    ) -> Result<Trades, Status> {
+        let range = TimeRange::from_request(start, end)?;
+        self.store.trades(symbol, range).await
+    }
+
+    /// Trade prices when `interval` is unset, otherwise one `series_type` value
+    /// per candle.
+    async fn fetch_series(
+        &self,
+        symbol: &str,
+        start: Option<&prost_types::Timestamp>,
+        end: Option<&prost_types::Timestamp>,
+        interval: Option<&prost_types::Duration>,
+        series_type: SeriesType,
+    ) -> Result<Series, Status> {
+        let Some(interval) = interval else {
+            let trades = self.fetch_trades(symbol, start, end).await?;
+            return Ok(Series {
+                timestamps: trades.timestamps,
+                values: trades.prices,
+            });
+        };
+        let candles = self
+            .fetch_candles(symbol, start, end, Some(interval))
+            .await?;
+        Ok(Series {
+            timestamps: candles.iter().map(|c| c.timestamp).collect(),
+            values: candles
+                .iter()
+                .map(|c| series_value(c, series_type))
+                .collect(),
+        })
+    }
+
+    async fn fetch_candles(
+        &self,
+        symbol: &str,
+        start: Option<&prost_types::Timestamp>,
+        end: Option<&prost_types::Timestamp>,
+        interval: Option<&prost_types::Duration>,
+    ) -> Result<Vec<store::Candle>, Status> {
+        let interval = Self::interval(interval)?;
+        let range = TimeRange::from_request(start, end)?;
+        self.planner.candles(symbol, range, interval).await
+    }
+
+    /// Stamps each band `band` returns with the candle it was fed.
+    fn band_points(
+        candles: Vec<store::Candle>,
+        mut band: impl FnMut(&store::Candle) -> Option<Band> + Send + 'static,
+    ) -> Points<BandDataPoint> {
+        Box::new(candles.into_iter().filter_map(move |candle| {
+            let band = band(&candle)?;
+            Some(BandDataPoint {
+                timestamp: candle.timestamp,
+                upper: band.upper,
+                middle: band.middle,
+                lower: band.lower,
+            })
+        }))
+    }
+
+    /// Looks up a registered indicator and checks `params` against it.
+    fn indicator(
+        &self,
+        name: &str,
+        params: &HashMap<String, f64>,
+    ) -> Result<(Arc<dyn Indicator>, Params), Status> {
+        let Some(indicator) = self.registry.get(name) else {
+            return Err(Status::not_found(format!("Unknown indicator `{name}`")));
+        };
+        let params = Params::resolve(indicator.params(), params)?;
+        indicator.validate(&params)?;
+        Ok((indicator, params))
+    }
+
+    /// Converts a request's bucket width, rejecting missing, non-positive and
+    /// overlong ones.
+    pub(crate) fn interval(interval: Option<&prost_types::Duration>) -> Result<Duration, Status> {
+        let Some(interval) = interval else {
+            return Err(Status::invalid_argument("interval is required"));
+        };
+        if interval.seconds < 0 || interval.nanos < 0 {
+            return Err(Status::invalid_argument("interval must be positive"));
+        }
+        let interval = Duration::new(interval.seconds as u64, interval.nanos as u32);
+        if interval.as_micros() == 0 {
+            return Err(Status::invalid_argument(
+                "interval must be at least one microsecond",
+            ));
+        }
+        if interval.as_micros() > i64::MAX as u128 {
+            return Err(Status::invalid_argument("interval is too long"));
+        }
+        Ok(interval)
+    }
+
+    async fn moving_average_points(
+        &self,
+        request: &GetMovingAverageRequest,
+    ) -> Result<Points<MovingAverageDataPoint>, Status> {
+        println!(
+            "Received SMA request for symbol {} with window size {}",
+            request.symbol, request.window_size
+        );
+        if request.window_size == 0 {
+            return Err(Status::invalid_argument("window_size must be positive"));
+        }
+
+        let series = self
+            .fetch_series(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+                request.series_type(),
+            )
+            .await?;
+        if series.values.is_empty() {
+            return Err(Status::not_found(
+                "No data found for the given symbol and timestamp range",
+            ));
+        }
+        // Over candles only full windows are reported, as Polygon does. Over
+        // trades the leading partial windows are kept for compatibility.
+        let window_size = request.window_size as usize;
+        let full_windows_only = request.interval.is_some();
+        if full_windows_only && series.values.len() < window_size {
+            return Err(Status::invalid_argument("Not enough data to compute SMA"));
+        }
+
+        let mut sma = SmaState::new(window_size);
+        let points = series.timestamps.into_iter().zip(series.values).filter_map(
+            move |(timestamp, value)| {
+                let full = sma.update(value);
+                let value = if full_windows_only {
+                    full
+                } else {
+                    sma.average()
+                }?;
+                Some(MovingAverageDataPoint { timestamp, value })
+            },
+        );
+
+        Ok(Box::new(points))
+    }
+
+    async fn ema_points(
+        &self,
+        request: &GetMovingAverageRequest,
+    ) -> Result<Points<MovingAverageDataPoint>, Status> {
+        println!(
+            "Received EMA request for symbol {} with window size {}",
+            request.symbol, request.window_size
+        );
+        let window_size = request.window_size as usize;
+        if window_size == 0 {
+            return Err(Status::invalid_argument("window_size must be positive"));
+        }
+
+        let series = self
+            .fetch_series(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+                request.series_type(),
+            )
+            .await?;
+        if series.values.len() < window_size {
+            return Err(Status::invalid_argument("Not enough data to compute EMA"));
+        }
+
+        // The first EMA value is the SMA of the first window, so it belongs to
+        // the trade or candle that completes that window.
+        let mut ema = EmaState::new(window_size);
+        let points = series.timestamps.into_iter().zip(series.values).filter_map(
+            move |(timestamp, value)| {
+                Some(MovingAverageDataPoint {
+                    timestamp,
+                    value: ema.update(value)?,
+                })
+            },
+        );
+
+        Ok(Box::new(points))
+    }
+
+    async fn rsi_points(
+        &self,
+        request: &GetMovingAverageRequest,
+    ) -> Result<Points<MovingAverageDataPoint>, Status> {
+        println!(
+            "Received RSI request for symbol {} with window size {}",
+            request.symbol, request.window_size
+        );
+        let window_size = request.window_size as usize;
+        if window_size == 0 {
+            return Err(Status::invalid_argument("window_size must be positive"));
+        }
+
+        let series = self
+            .fetch_series(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+                request.series_type(),
+            )
+            .await?;
+        // `window_size` changes need one more value than that.
+        if series.values.len() <= window_size {
+            return Err(Status::invalid_argument("Not enough data to compute RSI"));
+        }
+
+        let mut rsi = RsiState::new(window_size);
+        let points = series.timestamps.into_iter().zip(series.values).filter_map(
+            move |(timestamp, value)| {
+                Some(MovingAverageDataPoint {
+                    timestamp,
+                    value: rsi.update(value)?,
+                })
+            },
+        );
+
+        Ok(Box::new(points))
+    }
+
+    async fn bollinger_points(
+        &self,
+        request: &GetBollingerBandsRequest,
+    ) -> Result<Points<BandDataPoint>, Status> {
+        println!(
+            "Received Bollinger Bands request for symbol {} with window size {}",
+            request.symbol, request.window_size
+        );
+        let window_size = request.window_size as usize;
+        if window_size == 0 {
+            return Err(Status::invalid_argument("window_size must be positive"));
+        }
+        let num_std_dev = if request.num_std_dev == 0.0 {
+            2.0
+        } else {
+            request.num_std_dev
+        };
+
+        let series_type = request.series_type();
+        let candles = self
+            .fetch_candles(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+            )
+            .await?;
+        if candles.len() < window_size {
+            return Err(Status::invalid_argument(
+                "Not enough data to compute Bollinger Bands",
+            ));
+        }
+
+        let mut bands = BollingerState::new(window_size, num_std_dev);
+        Ok(Self::band_points(candles, move |candle| {
+            bands.update(series_value(candle, series_type))
+        }))
+    }
+
+    async fn atr_points(
+        &self,
+        request: &GetAtrRequest,
+    ) -> Result<Points<MovingAverageDataPoint>, Status> {
+        println!(
+            "Received ATR request for symbol {} with window size {}",
+            request.symbol, request.window_size
+        );
+        let window_size = request.window_size as usize;
+        if window_size == 0 {
+            return Err(Status::invalid_argument("window_size must be positive"));
+        }
+
+        let candles = self
+            .fetch_candles(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+            )
+            .await?;
+        // The first true range needs the previous candle's close.
+        if candles.len() <= window_size {
+            return Err(Status::invalid_argument("Not enough data to compute ATR"));
+        }
+
+        let mut atr = AtrState::new(window_size);
+        let points = candles.into_iter().filter_map(move |candle| {
+            Some(MovingAverageDataPoint {
+                timestamp: candle.timestamp,
+                value: atr.update(Hlc::from(&candle))?,
+            })
+        });
+        Ok(Box::new(points))
+    }
+
+    async fn keltner_points(
+        &self,
+        request: &GetKeltnerChannelsRequest,
+    ) -> Result<Points<BandDataPoint>, Status> {
+        println!(
+            "Received Keltner Channels request for symbol {} with EMA window {} and ATR window {}",
+            request.symbol, request.window_size, request.atr_window_size
+        );
+        let window_size = request.window_size as usize;
+        let atr_window_size = request.atr_window_size as usize;
+        if window_size == 0 || atr_window_size == 0 {
+            return Err(Status::invalid_argument(
+                "window_size and atr_window_size must be positive",
+            ));
+        }
+        let multiplier = if request.multiplier == 0.0 {
+            2.0
+        } else {
+            request.multiplier
+        };
+
+        let candles = self
+            .fetch_candles(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+            )
+            .await?;
+        let warm_up = indicators::keltner_warm_up(window_size, atr_window_size);
+        if candles.len() <= warm_up {
+            return Err(Status::invalid_argument(
+                "Not enough data to compute Keltner Channels",
+            ));
+        }
+
+        let mut bands = KeltnerState::new(window_size, atr_window_size, multiplier);
+        Ok(Self::band_points(candles, move |candle| {
+            bands.update(Hlc::from(candle))
+        }))
+    }
+
+    async fn macd_points(&self, request: &GetMacdRequest) -> Result<Vec<MacdDataPoint>, Status> {
+        let key = CacheKey {
+            rpc: "GetMacd",
+            symbol: request.symbol.clone(),
+            range: TimeRange::from_request(
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+            )?,
+            params: format!(
+                "{}/{}/{}/{:?}/{:?}",
+                request.fast_period,
+                request.slow_period,
+                request.signal_period,
+                request.interval,
+                request.series_type()
+            ),
+        };
+        let response = self
+            .cache
+            .get_or_compute(key, self.store.watermark(&request.symbol), async {
+                Ok(GetMacdResponse {
+                    points: self.compute_macd_points(request).await?,
+                    next_page_token: String::new(),
+                })
+            })
+            .await?;
+        Ok(response.points)
+    }
+
+    async fn compute_macd_points(
+        &self,
+        request: &GetMacdRequest,
+    ) -> Result<Vec<MacdDataPoint>, Status> {
+        println!(
+            "Received MACD request for symbol {} with fast period {}, slow period {}, and signal period {}",
+            request.symbol, request.fast_period, request.slow_period, request.signal_period
+        );
+        let periods = MacdPeriods::new(
+            request.fast_period as usize,
+            request.slow_period as usize,
+            request.signal_period as usize,
+        )?;
+
+        let Series {
+            timestamps,
+            values: prices,
+        } = self
+            .fetch_series(
+                &request.symbol,
+                request.start_timestamp.as_ref(),
+                request.end_timestamp.as_ref(),
+                request.interval.as_ref(),
+                request.series_type(),
+            )
+            .await?;
+        if prices.len() <= periods.warm_up() {
+            return Err(Status::invalid_argument("Not enough data to compute MACD"));
+        }
+
+        let points = timestamps
+            .into_iter()
+            .skip(periods.warm_up())
+            .zip(indicators::macd(&prices, periods))
+            .map(|(timestamp, row)| MacdDataPoint {
+                timestamp,
+                macd_line: row.macd,
+                signal_line: row.signal,
+                histogram: row.histogram,
+            })
+            .collect();
+        Ok(points)
+    }
+
+    /// Every `ComputeIndicator` point for `request`, unpaged.
+    pub(crate) async fn indicator_response(
+        &self,
+        request: &ComputeIndicatorRequest,
+    ) -> Result<ComputeIndicatorResponse, Status> {
+        let IndicatorPoints {
+            outputs,
+            warm_up,
+            points,
+        } = self.indicator_points(request).await?;
+        Ok(ComputeIndicatorResponse {
+            outputs,
+            warm_up,
+            points: points.collect(),
+            next_page_token: String::new(),
+        })
+    }
+
+    /// Runs the indicator's streaming state when it has one, so points are
+    /// computed as they are pulled.
+    async fn indicator_points(
+        &self,
+        request: &ComputeIndicatorRequest,
+    ) -> Result<IndicatorPoints, Status> {
+        println!(
+            "Received {} indicator request for symbol {} with params {:?}",
+            request.name, request.symbol, request.params
+        );
+        let (indicator, params) = self.indicator(&request.name, &request.params)?;
+
+        let start = request.start_timestamp.as_ref();
+        let end = request.end_timestamp.as_ref();
+        let (series, candles) = match (indicator.input(), request.interval.as_ref()) {
+            (Input::Series, None) => {
+                let trades = self.fetch_trades(&request.symbol, start, end).await?;
+                (
+                    Series {
+                        timestamps: trades.timestamps,
+                        values: trades.prices,
+                    },
+                    Vec::new(),
+                )
+            }
+            (Input::Candles, None) => {
+                return Err(Status::invalid_argument(format!(
+                    "{} requires an interval",
+                    indicator.name()
+                )));
+            }
+            (_, interval) => {
+                let candles = self
+                    .fetch_candles(&request.symbol, start, end, interval)
+                    .await?;
+                let series = Series {
+                    timestamps: candles.iter().map(|c| c.timestamp).collect(),
+                    values: candles
+                        .iter()
+                        .map(|c| series_value(c, request.series_type()))
+                        .collect(),
+                };
+                (series, candles)
+            }
+        };
+
+        let warm_up = indicator.warm_up(&params);
+        if series.values.len() <= warm_up {
+            return Err(Status::invalid_argument(format!(
+                "Not enough data to compute {}",
+                indicator.name().to_uppercase()
+            )));
+        }
+
+        let points: Points<IndicatorPoint> = match indicator.streaming(&params) {
+            Some(mut state) => Box::new(
+                series
+                    .timestamps
+                    .into_iter()
+                    .zip(series.values)
+                    .enumerate()
+                    .filter_map(move |(i, (timestamp, value))| {
+                        let values = state.update(value, candles.get(i))?;
+                        Some(IndicatorPoint { timestamp, values })
+                    }),
+            ),
+            None => {
+                let inputs = Inputs {
+                    values: &series.values,
+                    candles: &candles,
+                };
+                let rows = indicator.compute(&inputs, &params);
+                Box::new(
+                    series
+                        .timestamps
+                        .into_iter()
+                        .skip(warm_up)
+                        .zip(rows)
+                        .map(|(timestamp, values)| IndicatorPoint { timestamp, values }),
+                )
+            }
+        };
+        Ok(IndicatorPoints {
+            outputs: indicator.outputs().iter().map(|o| o.to_string()).collect(),
+            warm_up: warm_up as u32,
+            points,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl AnalyticsService for AnalyticsServiceHandler {
+    async fn get_trade_analytics(
+        &self,
+        request: Request<GetTradeAnalyticsRequest>,
+    ) -> Result<Response<GetTradeAnalyticsResponse>, Status> {
+        let req = request.into_inner();
+        println!("Received request for symbol {}", req.symbol);
+        let range =
+            TimeRange::from_request(req.start_timestamp.as_ref(), req.end_timestamp.as_ref())?;
+        let key = CacheKey {
+            rpc: "GetTradeAnalytics",
+            symbol: req.symbol.clone(),
+            range,
+            params: String::new(),
+        };
+        let response = self
+            .cache
+            .get_or_compute(key, self.store.watermark(&req.symbol), async {
+                let summary = self.planner.summary(&req.symbol, range).await?;
+                if summary.trade_count == 0 {
+                    return Err(Status::not_found(
+                        "No data found for the given symbol and timestamp range",
+                    ));
+                }
+                Ok(GetTradeAnalyticsResponse {
+                    total_volume_in_quotes: summary.quote_volume,
+                    vwap: summary.vwap(),
+                    trades_count: summary.trade_count,
+                })
+            })
+            .await?;
+        Ok(Response::new(response))
+    }
+
+    async fn get_moving_average(
+        &self,
+        request: Request<GetMovingAverageRequest>,
+    ) -> Result<Response<GetMovingAverageResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("sma", &request)?;
+        let points = self.moving_average_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.value));
+        Ok(Response::new(GetMovingAverageResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamMovingAverageStream = BoxStream<'static, Result<GetMovingAverageResponse, Status>>;
+    async fn stream_moving_average(
+        &self,
+        request: Request<GetMovingAverageRequest>,
+    ) -> Result<Response<Self::StreamMovingAverageStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("sma", &request)?;
+        let points = self.moving_average_points(&request).await?;
+        let responses =
+            paging
+                .chunks(points, |p| (p.timestamp, p.value))
+                .map(|(points, next_page_token)| {
+                    Ok(GetMovingAverageResponse {
+                        points,
+                        next_page_token,
+                    })
+                });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn get_ema(
+        &self,
+        request: Request<GetMovingAverageRequest>,
+    ) -> Result<Response<GetMovingAverageResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("ema", &request)?;
+        let points = self.ema_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.value));
+        Ok(Response::new(GetMovingAverageResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamEmaStream = BoxStream<'static, Result<GetMovingAverageResponse, Status>>;
+    async fn stream_ema(
+        &self,
+        request: Request<GetMovingAverageRequest>,
+    ) -> Result<Response<Self::StreamEmaStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("ema", &request)?;
+        let points = self.ema_points(&request).await?;
+        let responses =
+            paging
+                .chunks(points, |p| (p.timestamp, p.value))
+                .map(|(points, next_page_token)| {
+                    Ok(GetMovingAverageResponse {
+                        points,
+                        next_page_token,
+                    })
+                });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    type SubscribeToTradesStream = TradeStream;
+    async fn subscribe_to_trades(
+        &self,
+        request: Request<SubscribeToTradesRequest>,
+    ) -> Result<Response<TradeStream>, Status> {
+        let request = request.into_inner();
+        let trade_stream = self.feed.subscribe(&request.symbol).await?;
+        Ok(Response::new(trade_stream))
+    }
+
+    type SubscribeToIndicatorStream = BoxStream<'static, Result<IndicatorUpdate, Status>>;
+    async fn subscribe_to_indicator(
+        &self,
+        request: Request<SubscribeToIndicatorRequest>,
+    ) -> Result<Response<Self::SubscribeToIndicatorStream>, Status> {
+        let request = request.into_inner();
+        println!(
+            "Received {} subscription for symbol {} with params {:?}",
+            request.name, request.symbol, request.params
+        );
+        let (indicator, params) = self.indicator(&request.name, &request.params)?;
+        let interval = Self::interval(request.interval.as_ref())?;
+        let series_type = request.series_type();
+
+        // Subscribe before reading the watermark, so every trade the store has
+        // not ingested by then reaches the feed. History stops at the watermark
+        // rather than now: the sink buffers trades before writing them, and
+        // those would otherwise be missing from history and skipped live. A
+        // trade published before the subscription and ingested only after
+        // the watermark was read is in neither.
+        let mut trades = self.feed.subscribe(&request.symbol).await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let start = match request.start_timestamp.as_ref() {
+            Some(start) => store::to_micros(start),
+            None => {
+                let candles = (indicator.warm_up(&params) + 1).max(live::HISTORY_CANDLES);
+                now.saturating_sub(candles as u64 * interval.as_micros() as u64)
+            }
+        };
+        if start > now {
+            return Err(Status::invalid_argument(
+                "start_timestamp must not be in the future",
+            ));
+        }
+        // A store that does not know its watermark is taken to be current.
+        let watermark = self
+            .store
+            .watermark(&request.symbol)
+            .await?
+            .unwrap_or(now)
+            .min(now);
+        let history = if watermark < start {
+            Vec::new()
+        } else {
+            let range = TimeRange {
+                start_micros: start,
+                end_micros: watermark,
+            };
+            self.planner
+                .candles(&request.symbol, range, interval)
+                .await?
+        };
+        let seeded_until = watermark.max(start.saturating_sub(1));
+
+        let mut live = LiveIndicator::new(
+            indicator,
+            params,
+            series_type,
+            interval,
+            history,
+            seeded_until,
+        );
+        let provisional = request.provisional;
+        let updates = async_stream::try_stream! {
+            if let Some(update) = live.latest() {
+                yield update;
+            }
+            while let Some(trade) = trades.next().await {
+                let trade = trade?;
+                if let Some(update) = live.push(&trade) {
+                    yield update;
+                }
+                if provisional
+                    && trade.exchange_timestamp > seeded_until
+                    && let Some(update) = live.provisional()
+                {
+                    yield update;
+                }
+            }
+        };
+        Ok(Response::new(Box::pin(updates)))
+    }
+
+    async fn get_macd(
+        &self,
+        request: Request<GetMacdRequest>,
+    ) -> Result<Response<GetMacdResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("macd", &request)?;
+        let points = self.macd_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.macd_line));
+        Ok(Response::new(GetMacdResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamMacdStream = BoxStream<'static, Result<GetMacdResponse, Status>>;
+    async fn stream_macd(
+        &self,
+        request: Request<GetMacdRequest>,
+    ) -> Result<Response<Self::StreamMacdStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("macd", &request)?;
+        let points = self.macd_points(&request).await?;
+        let responses = paging.chunks(points, |p| (p.timestamp, p.macd_line)).map(
+            |(points, next_page_token)| {
+                Ok(GetMacdResponse {
+                    points,
+                    next_page_token,
+                })
+            },
+        );
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn get_rsi(
+        &self,
+        request: Request<GetMovingAverageRequest>,
+    ) -> Result<Response<GetMovingAverageResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("rsi", &request)?;
+        let points = self.rsi_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.value));
+        Ok(Response::new(GetMovingAverageResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamRsiStream = BoxStream<'static, Result<GetMovingAverageResponse, Status>>;
+    async fn stream_rsi(
+        &self,
+        request: Request<GetMovingAverageRequest>,
+    ) -> Result<Response<Self::StreamRsiStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("rsi", &request)?;
+        let points = self.rsi_points(&request).await?;
+        let responses =
+            paging
+                .chunks(points, |p| (p.timestamp, p.value))
+                .map(|(points, next_page_token)| {
+                    Ok(GetMovingAverageResponse {
+                        points,
+                        next_page_token,
+                    })
+                });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn get_bollinger_bands(
+        &self,
+        request: Request<GetBollingerBandsRequest>,
+    ) -> Result<Response<GetBandsResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("bollinger", &request)?;
+        let points = self.bollinger_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.middle));
+        Ok(Response::new(GetBandsResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamBollingerBandsStream = BoxStream<'static, Result<GetBandsResponse, Status>>;
+    async fn stream_bollinger_bands(
+        &self,
+        request: Request<GetBollingerBandsRequest>,
+    ) -> Result<Response<Self::StreamBollingerBandsStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("bollinger", &request)?;
+        let points = self.bollinger_points(&request).await?;
+        let responses =
+            paging
+                .chunks(points, |p| (p.timestamp, p.middle))
+                .map(|(points, next_page_token)| {
+                    Ok(GetBandsResponse {
+                        points,
+                        next_page_token,
+                    })
+                });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn get_atr(
+        &self,
+        request: Request<GetAtrRequest>,
+    ) -> Result<Response<GetMovingAverageResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("atr", &request)?;
+        let points = self.atr_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.value));
+        Ok(Response::new(GetMovingAverageResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamAtrStream = BoxStream<'static, Result<GetMovingAverageResponse, Status>>;
+    async fn stream_atr(
+        &self,
+        request: Request<GetAtrRequest>,
+    ) -> Result<Response<Self::StreamAtrStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("atr", &request)?;
+        let points = self.atr_points(&request).await?;
+        let responses =
+            paging
+                .chunks(points, |p| (p.timestamp, p.value))
+                .map(|(points, next_page_token)| {
+                    Ok(GetMovingAverageResponse {
+                        points,
+                        next_page_token,
+                    })
+                });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn get_keltner_channels(
+        &self,
+        request: Request<GetKeltnerChannelsRequest>,
+    ) -> Result<Response<GetBandsResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("keltner", &request)?;
+        let points = self.keltner_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| (p.timestamp, p.middle));
+        Ok(Response::new(GetBandsResponse {
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamKeltnerChannelsStream = BoxStream<'static, Result<GetBandsResponse, Status>>;
+    async fn stream_keltner_channels(
+        &self,
+        request: Request<GetKeltnerChannelsRequest>,
+    ) -> Result<Response<Self::StreamKeltnerChannelsStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("keltner", &request)?;
+        let points = self.keltner_points(&request).await?;
+        let responses =
+            paging
+                .chunks(points, |p| (p.timestamp, p.middle))
+                .map(|(points, next_page_token)| {
+                    Ok(GetBandsResponse {
+                        points,
+                        next_page_token,
+                    })
+                });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn get_candles(
+        &self,
+        request: Request<GetCandlesRequest>,
+    ) -> Result<Response<GetCandlesResponse>, Status> {
+        let request = request.into_inner();
+        let interval = Self::interval(request.interval.as_ref())?;
+        println!(
+            "Received candles request for symbol {} with interval {:?}",
+            request.symbol, interval
+        );
+        let range = TimeRange::from_request(
+            request.start_timestamp.as_ref(),
+            request.end_timestamp.as_ref(),
+        )?;
+
+        let candles = self
+            .planner
+            .candles(&request.symbol, range, interval)
+            .await?;
+        if candles.is_empty() {
+            return Err(Status::not_found(
+                "No data found for the given symbol and timestamp range",
+            ));
+        }
+
+        let candles = candles
+            .into_iter()
+            .map(|candle| Candle {
+                timestamp: candle.timestamp,
+                open: candle.open,
+                high: candle.high,
+                low: candle.low,
+                close: candle.close,
+                volume: candle.volume,
+                vwap: candle.vwap(),
+                trades_count: candle.trade_count,
+                buy_volume: candle.buy_volume,
+                sell_volume: candle.sell_volume,
+            })
+            .collect();
+        Ok(Response::new(GetCandlesResponse { candles }))
+    }
+
+    async fn compute_indicator(
+        &self,
+        request: Request<ComputeIndicatorRequest>,
+    ) -> Result<Response<ComputeIndicatorResponse>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("indicator", &request)?;
+        let IndicatorPoints {
+            outputs,
+            warm_up,
+            points,
+        } = self.indicator_points(&request).await?;
+        let (points, next_page_token) = paging.page(points, |p| {
+            (p.timestamp, p.values.first().copied().unwrap_or_default())
+        });
+        Ok(Response::new(ComputeIndicatorResponse {
+            outputs,
+            warm_up,
+            points,
+            next_page_token,
+        }))
+    }
+
+    type StreamIndicatorStream = BoxStream<'static, Result<ComputeIndicatorResponse, Status>>;
+    async fn stream_indicator(
+        &self,
+        request: Request<ComputeIndicatorRequest>,
+    ) -> Result<Response<Self::StreamIndicatorStream>, Status> {
+        let request = request.into_inner();
+        let paging = Paging::from_request("indicator", &request)?;
+        let IndicatorPoints {
+            outputs,
+            warm_up,
+            points,
+        } = self.indicator_points(&request).await?;
+        let responses = paging
+            .chunks(points, |p| {
+                (p.timestamp, p.values.first().copied().unwrap_or_default())
+            })
+            .map(move |(points, next_page_token)| {
+                Ok(ComputeIndicatorResponse {
+                    outputs: outputs.clone(),
+                    warm_up,
+                    points,
+                    next_page_token,
+                })
+            });
+        Ok(Response::new(Box::pin(stream::iter(responses))))
+    }
+
+    async fn list_indicators(
+        &self,
+        _request: Request<ListIndicatorsRequest>,
+    ) -> Result<Response<ListIndicatorsResponse>, Status> {
+        let indicators = self
+            .registry
+            .iter()
+            .map(|indicator| IndicatorDescriptor {
+                name: indicator.name().to_string(),
+                description: indicator.description().to_string(),
+                parameters: indicator
+                    .params()
+                    .iter()
+                    .map(|spec| IndicatorParameter {
+                        name: spec.name.to_string(),
+                        description: spec.description.to_string(),
+                        default_value: spec.default,
+                        min_value: spec.min,
+                        integer: spec.integer,
+                    })
+                    .collect(),
+                input: match indicator.input() {
+                    Input::Series => IndicatorInput::Series,
+                    Input::Candles => IndicatorInput::Candles,
+                } as i32,
+                outputs: indicator.outputs().iter().map(|o| o.to_string()).collect(),
+            })
+            .collect();
+        Ok(Response::new(ListIndicatorsResponse { indicators }))
+    }
+
+    async fn execute_query(
+        &self,
+        request: Request<ExecuteQueryRequest>,
+    ) -> Result<Response<ExecuteQueryResponse>, Status> {
+        let request = request.into_inner();
+        println!("Received query: {}", request.sql);
+        let mut limits = self.query_limits;
+        if request.max_rows > 0 {
+            limits.max_rows = limits.max_rows.min(request.max_rows as usize);
+        }
+        let result = query::execute(self.planner.context(), &request.sql, limits).await?;
+        Ok(Response::new(ExecuteQueryResponse {
+            arrow_ipc: result.arrow_ipc,
+            rows: result.rows as u64,
+            truncated: result.truncated,
+        }))
+    }
+}
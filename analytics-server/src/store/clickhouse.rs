@@ -0,0 +1,563 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use clickhouse::Client;
+use clickhouse::query::{Query, RowCursor};
+use datafusion::arrow::array::{RecordBatch, RecordBatchOptions};
+use datafusion::arrow::buffer::Buffer;
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::ipc::reader::StreamDecoder;
+use datafusion::catalog::{Session, TableProvider};
+use datafusion::datasource::TableType;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::{Expr, TableProviderFilterPushDown};
+use datafusion::physical_expr::LexOrdering;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::{PartitionStream, StreamingTableExec};
+use tonic::Status;
+
+use super::table::{TradeFilter, external, trades_schema};
+use super::{Aggregation, Candle, TimeRange, TradeStore, TradeSummary, Trades};
+use crate::data::trade::Side;
+
+/// Reads from `default.trades`. `FINAL` collapses trades redelivered by the
+/// sink before ClickHouse has merged them away.
+/// Candles of a width the sink rolls up are read from its `candles_*` tables
+/// once settled and while still retained; see [`Rollup`].
+pub struct ClickHouseStore {
+    client: Client,
+}
+
+impl ClickHouseStore {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl TradeStore for ClickHouseStore {
+    async fn trades(&self, symbol: &str, range: TimeRange) -> Result<Trades, Status> {
+        println!(
+            "[ClickHouseStore::trades] Querying trades for symbol: {} from {} to {}",
+            symbol, range.start_micros, range.end_micros
+        );
+        #[derive(Debug, serde::Deserialize, clickhouse::Row)]
+        struct Row {
+            exchange_timestamp: u64,
+            price: f64,
+            quantity: f64,
+            taker_side: String,
+        }
+        let mut cursor: RowCursor<Row> = self
+            .client
+            .query(
+                "SELECT exchange_timestamp, price, quantity, taker_side
+                 FROM default.trades FINAL
+                 WHERE symbol = ? AND exchange_timestamp >= ? AND exchange_timestamp <= ?
+                 ORDER BY exchange_timestamp",
+            )
+            .bind(symbol)
+            .bind(range.start_micros)
+            .bind(range.end_micros)
+            .fetch()
+            .map_err(|e| Status::internal(format!("Error fetching data: {}", e)))?;
+        let mut trades = Trades::default();
+        while let Some(row) = cursor
+            .next()
+            .await
+            .map_err(|e| Status::internal(format!("Error fetching row: {}", e)))?
+        {
+            trades.push(
+                row.exchange_timestamp,
+                row.price,
+                row.quantity,
+                Side::from_str_name(&row.taker_side).unwrap_or_default(),
+            );
+        }
+        Ok(trades)
+    }
+
+    /// The latest trade of the exchange furthest behind, so that a lagging
+    /// exchange keeps its windows open. Exchanges more than
+    /// [`STALE_EXCHANGE`] behind the symbol's latest trade are taken to have
+    /// stopped trading it.
+    async fn watermark(&self, symbol: &str) -> Result<Option<u64>, Status> {
+        let latest = self
+            .client
+            .query(
+                "SELECT min(latest) FROM (
+                     SELECT max(exchange_timestamp) AS latest
+                     FROM default.trades
+                     WHERE symbol = ?
+                     GROUP BY exchange
+                 )
+                 WHERE latest + ? >= (
+                     SELECT max(exchange_timestamp) FROM default.trades WHERE symbol = ?
+                 )",
+            )
+            .bind(symbol)
+            .bind(STALE_EXCHANGE.as_micros() as u64)
+            .bind(symbol)
+            .fetch_one::<u64>()
+            .await
+            .map_err(|e| Status::internal(format!("Error fetching watermark: {}", e)))?;
+        // `max` over no rows is 0.
+        Ok((latest > 0).then_some(latest))
+    }
+
+    fn pushes_down(&self, _aggregation: Aggregation) -> bool {
+        true
+    }
+
+    fn table(&self) -> Option<Arc<dyn TableProvider>> {
+        Some(Arc::new(ClickHouseTable::new(self.client.clone())))
+    }
+
+    async fn summary(&self, symbol: &str, range: TimeRange) -> Result<TradeSummary, Status> {
+        println!(
+            "[ClickHouseStore::summary] Aggregating trades for symbol: {} from {} to {}",
+            symbol, range.start_micros, range.end_micros
+        );
+        #[derive(Debug, serde::Deserialize, clickhouse::Row)]
+        struct Row {
+            volume: f64,
+            quote_volume: f64,
+            trade_count: u64,
+        }
+        let row: Row = self
+            .client
+            .query(
+                "SELECT
+                    sum(quantity) AS volume,
+                    sum(price * quantity) AS quote_volume,
+                    count() AS trade_count
+                 FROM default.trades FINAL
+                 WHERE symbol = ? AND exchange_timestamp >= ? AND exchange_timestamp <= ?",
+            )
+            .bind(symbol)
+            .bind(range.start_micros)
+            .bind(range.end_micros)
+            .fetch_one()
+            .await
+            .map_err(|e| Status::internal(format!("Error aggregating trades: {}", e)))?;
+        Ok(TradeSummary {
+            volume: row.volume,
+            quote_volume: row.quote_volume,
+            trade_count: row.trade_count,
+        })
+    }
+
+    async fn candles(
+        &self,
+        symbol: &str,
+        range: TimeRange,
+        interval: Duration,
+    ) -> Result<Vec<Candle>, Status> {
+        let Some(rollup) = ROLLUPS.iter().find(|rollup| rollup.interval == interval) else {
+            return self.bucket_trades(symbol, range, interval).await;
+        };
+        // Whole, settled candles still kept by the rollup come from it; candles
+        // cut by the range, recent ones and expired ones are bucketed from
+        // trades as usual.
+        let width = interval.as_micros() as u64;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let settled = now.saturating_sub(rollup.settled.as_micros() as u64);
+        let retained = rollup.retained.map_or(0, |retained| {
+            now.saturating_sub(retained.as_micros() as u64)
+        });
+        let Some(first) = range
+            .start_micros
+            .max(retained)
+            .div_ceil(width)
+            .checked_mul(width)
+        else {
+            return self.bucket_trades(symbol, range, interval).await;
+        };
+        let end = (range.end_micros.saturating_add(1) / width * width).min(settled / width * width);
+        if first >= end {
+            return self.bucket_trades(symbol, range, interval).await;
+        }
+
+        let mut candles = Vec::new();
+        if range.start_micros < first {
+            let head = TimeRange {
+                start_micros: range.start_micros,
+                end_micros: first - 1,
+            };
+            candles.extend(self.bucket_trades(symbol, head, interval).await?);
+        }
+        candles.extend(self.rolled_up(rollup, symbol, first, end).await?);
+        if end <= range.end_micros {
+            let tail = TimeRange {
+                start_micros: end,
+                end_micros: range.end_micros,
+            };
+            candles.extend(self.bucket_trades(symbol, tail, interval).await?);
+        }
+        Ok(candles)
+    }
+
+    async fn symbols(&self) -> Result<Vec<String>, Status> {
+        self.client
+            .query("SELECT DISTINCT symbol FROM default.trades ORDER BY symbol")
+            .fetch_all::<String>()
+            .await
+            .map_err(|e| Status::internal(format!("Error fetching symbols: {}", e)))
+    }
+}
+
+/// A candle table kept by the sink's migrations, rebuilt from
+/// deduplicated trades on a schedule.
+///
+/// A refresh only recomputes its lookback window, so a trade stored later
+/// than that is missing from its settled bucket until the sink rebuilds the
+/// range (`--rebuild-candles-from`/`--rebuild-candles-to`).
+struct Rollup {
+    interval: Duration,
+    table: &'static str,
+    /// How long after it starts a bucket stops being refreshed: its lookback
+    /// window plus one refresh.
+    settled: Duration,
+    /// How far back the table is complete: its TTL less a day, so buckets
+    /// about to expire are not read. `None` keeps every bucket.
+    retained: Option<Duration>,
+}
+
+/// How far an exchange's latest trade may trail the symbol's before
+/// [`ClickHouseStore::watermark`] ignores the exchange.
+const STALE_EXCHANGE: Duration = Duration::from_secs(60 * 60);
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+const ROLLUPS: &[Rollup] = &[
+    Rollup {
+        interval: Duration::from_secs(1),
+        table: "default.candles_1s",
+        settled: Duration::from_secs(11 * 60),
+        retained: Some(Duration::from_secs(6 * DAY.as_secs())),
+    },
+    Rollup {
+        interval: Duration::from_secs(60),
+        table: "default.candles_1m",
+        settled: Duration::from_secs(11 * 60),
+        retained: Some(Duration::from_secs(89 * DAY.as_secs())),
+    },
+    Rollup {
+        interval: Duration::from_secs(5 * 60),
+        table: "default.candles_5m",
+        settled: Duration::from_secs(16 * 60),
+        retained: Some(Duration::from_secs(364 * DAY.as_secs())),
+    },
+    Rollup {
+        interval: Duration::from_secs(60 * 60),
+        table: "default.candles_1h",
+        settled: Duration::from_secs(125 * 60),
+        retained: None,
+    },
+    Rollup {
+        interval: Duration::from_secs(24 * 60 * 60),
+        table: "default.candles_1d",
+        settled: Duration::from_secs((2 * 24 * 60 + 15) * 60),
+        retained: None,
+    },
+];
+
+#[derive(Debug, serde::Deserialize, clickhouse::Row)]
+struct CandleRow {
+    bucket_start: u64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+    quote_volume: f64,
+    trade_count: u64,
+    buy_volume: f64,
+    sell_volume: f64,
+}
+
+impl From<CandleRow> for Candle {
+    fn from(row: CandleRow) -> Self {
+        Candle {
+            timestamp: row.bucket_start,
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+            volume: row.volume,
+            quote_volume: row.quote_volume,
+            trade_count: row.trade_count,
+            buy_volume: row.buy_volume,
+            sell_volume: row.sell_volume,
+        }
+    }
+}
+
+impl ClickHouseStore {
+    /// Candles bucketed from `trades FINAL` at query time.
+    async fn bucket_trades(
+        &self,
+        symbol: &str,
+        range: TimeRange,
+        interval: Duration,
+    ) -> Result<Vec<Candle>, Status> {
+        let interval_micros = interval.as_micros() as u64;
+        println!(
+            "[ClickHouseStore::candles] Querying {}us candles for symbol: {} from {} to {}",
+            interval_micros, symbol, range.start_micros, range.end_micros
+        );
+        let rows: Vec<CandleRow> = self
+            .client
+            .query(
+                "SELECT
+                    intDiv(exchange_timestamp, ?) * ? AS bucket_start,
+                    argMin(price, exchange_timestamp) AS open,
+                    max(price) AS high,
+                    min(price) AS low,
+                    argMax(price, exchange_timestamp) AS close,
+                    sum(quantity) AS volume,
+                    sum(price * quantity) AS quote_volume,
+                    count() AS trade_count,
+                    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+                    sumIf(quantity, taker_side = 'SELL') AS sell_volume
+                 FROM default.trades FINAL
+                 WHERE symbol = ? AND exchange_timestamp >= ? AND exchange_timestamp <= ?
+                 GROUP BY bucket_start
+                 ORDER BY bucket_start",
+            )
+            .bind(interval_micros)
+            .bind(interval_micros)
+            .bind(symbol)
+            .bind(range.start_micros)
+            .bind(range.end_micros)
+            .fetch_all()
+            .await
+            .map_err(|e| Status::internal(format!("Error fetching candles: {}", e)))?;
+        Ok(rows.into_iter().map(Candle::from).collect())
+    }
+
+    /// `rollup`'s candles starting in `[start, end)`, in microseconds, with
+    /// every exchange's row for a bucket combined.
+    async fn rolled_up(
+        &self,
+        rollup: &Rollup,
+        symbol: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<Candle>, Status> {
+        println!(
+            "[ClickHouseStore::candles] Reading {} for symbol: {} from {} to {}",
+            rollup.table, symbol, start, end
+        );
+        let rows: Vec<CandleRow> = self
+            .client
+            .query(&format!(
+                "SELECT
+                    toUInt64(toUnixTimestamp(bucket)) * 1000000 AS bucket_start,
+                    argMin(open, open_time) AS open,
+                    max(high) AS high,
+                    min(low) AS low,
+                    argMax(close, close_time) AS close,
+                    sum(volume) AS volume,
+                    sum(quote_volume) AS quote_volume,
+                    sum(trade_count) AS trade_count,
+                    sum(buy_volume) AS buy_volume,
+                    sum(sell_volume) AS sell_volume
+                 FROM {} FINAL
+                 WHERE symbol = ? AND bucket >= toDateTime(?, 'UTC') AND bucket < toDateTime(?, 'UTC')
+                 GROUP BY bucket_start
+                 ORDER BY bucket_start",
+                rollup.table
+            ))
+            .bind(symbol)
+            .bind(start / 1_000_000)
+            .bind(end / 1_000_000)
+            .fetch_all()
+            .await
+            .map_err(|e| Status::internal(format!("Error fetching candles: {}", e)))?;
+        Ok(rows.into_iter().map(Candle::from).collect())
+    }
+}
+
+/// `default.trades` as a DataFusion table. Scans stream ClickHouse's
+/// `ArrowStream` output, reading only the projected columns and filtering by
+/// symbol and time range in ClickHouse.
+#[derive(Clone)]
+pub struct ClickHouseTable {
+    client: Client,
+}
+
+impl ClickHouseTable {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl fmt::Debug for ClickHouseTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClickHouseTable").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl TableProvider for ClickHouseTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        trades_schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> datafusion::error::Result<Vec<TableProviderFilterPushDown>> {
+        Ok(TradeFilter::pushdown(filters))
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> datafusion::error::Result<Arc<dyn ExecutionPlan>> {
+        let schema = match projection {
+            Some(projection) => Arc::new(trades_schema().project(projection)?),
+            None => trades_schema(),
+        };
+        let scan = ClickHouseScan {
+            client: self.client.clone(),
+            schema: schema.clone(),
+            filter: TradeFilter::new(filters),
+            limit,
+        };
+        Ok(Arc::new(StreamingTableExec::try_new(
+            schema,
+            vec![Arc::new(scan)],
+            None,
+            Vec::<LexOrdering>::new(),
+            false,
+            limit,
+        )?))
+    }
+}
+
+/// One scan of [`ClickHouseTable`].
+#[derive(Clone)]
+struct ClickHouseScan {
+    client: Client,
+    schema: SchemaRef,
+    filter: TradeFilter,
+    limit: Option<usize>,
+}
+
+impl fmt::Debug for ClickHouseScan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClickHouseScan")
+            .field("schema", &self.schema)
+            .field("filter", &self.filter)
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClickHouseScan {
+    fn query(&self, range: TimeRange) -> Query {
+        let columns: Vec<&str> = self
+            .schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        // ClickHouse needs at least one column, even to count rows.
+        let columns = if columns.is_empty() {
+            "exchange_timestamp".to_string()
+        } else {
+            columns.join(", ")
+        };
+        let mut sql = format!(
+            "SELECT {columns} FROM default.trades FINAL
+             WHERE exchange_timestamp >= ? AND exchange_timestamp <= ?"
+        );
+        if let Some(symbols) = &self.filter.symbols {
+            sql.push_str(&format!(
+                " AND symbol IN ({})",
+                vec!["?"; symbols.len()].join(", ")
+            ));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        println!(
+            "[ClickHouseTable::scan] Streaming {} from {} to {}",
+            columns, range.start_micros, range.end_micros
+        );
+        let mut query = self
+            .client
+            .query(&sql)
+            .bind(range.start_micros)
+            .bind(range.end_micros);
+        for symbol in self.filter.symbols.iter().flatten() {
+            query = query.bind(symbol.as_str());
+        }
+        query
+    }
+}
+
+impl PartitionStream for ClickHouseScan {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let schema = self.schema.clone();
+        let query = self.filter.range().map(|range| self.query(range));
+        let batches = async_stream::try_stream! {
+            if let Some(query) = query {
+                let mut cursor = query.fetch_bytes("ArrowStream").map_err(external)?;
+                let mut decoder = StreamDecoder::new();
+                while let Some(bytes) = cursor.next().await.map_err(external)? {
+                    let mut buffer = Buffer::from(bytes);
+                    while !buffer.is_empty() {
+                        if let Some(batch) = decoder.decode(&mut buffer)? {
+                            yield conform(&batch, &schema)?;
+                        }
+                    }
+                }
+                decoder.finish()?;
+            }
+        };
+        Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), batches))
+    }
+}
+
+/// Casts a batch as ClickHouse encoded it (`UInt64` timestamps, strings
+/// possibly as binary) to the table's types.
+fn conform(batch: &RecordBatch, schema: &SchemaRef) -> Result<RecordBatch, ArrowError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, column)| cast(column, field.data_type()))
+        .collect::<Result<Vec<_>, _>>()?;
+    RecordBatch::try_new_with_options(
+        schema.clone(),
+        columns,
+        &RecordBatchOptions::new().with_row_count(Some(batch.num_rows())),
+    )
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use datafusion::arrow::array::{Array, Float64Array, StringArray, TimestampNanosecondArray};
+use datafusion::arrow::ipc::reader::FileReader;
+use tonic::Status;
+
+use super::{Aggregation, Candle, TimeRange, TradeStore, TradeSummary, Trades, fold_candles};
+use crate::data::trade::Side;
+
+/// Keeps every trade in memory, keyed by symbol. Used for tests, demos and
+/// offline analysis of exported Arrow files.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    trades: BTreeMap<String, Trades>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a trade. Trades may be inserted in any order.
+    pub fn insert(
+        &mut self,
+        symbol: &str,
+        timestamp_micros: u64,
+        price: f64,
+        quantity: f64,
+        taker_side: Side,
+    ) {
+        let trades = self.trades.entry(symbol.to_string()).or_default();
+        let index = trades
+            .timestamps
+            .partition_point(|t| *t <= timestamp_micros);
+        trades.timestamps.insert(index, timestamp_micros);
+        trades.prices.insert(index, price);
+        trades.quantities.insert(index, quantity);
+        trades.taker_sides.insert(index, taker_side);
+    }
+
+    /// Loads an Arrow IPC file with `symbol` (Utf8), `timestamp`
+    /// (Timestamp(Nanosecond)), `price` and `quantity` (Float64) columns, and
+    /// optionally `taker_side` (Utf8, `BUY` or `SELL`).
+    pub fn from_arrow_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let reader = FileReader::try_new(File::open(path)?, None)?;
+        let mut store = Self::new();
+        for batch in reader {
+            let batch = batch?;
+            let column = |name: &str| {
+                batch
+                    .column_by_name(name)
+                    .ok_or_else(|| format!("Arrow file is missing the `{name}` column"))
+            };
+            let symbols = column("symbol")?
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or("`symbol` must be Utf8")?;
+            let timestamps = column("timestamp")?
+                .as_any()
+                .downcast_ref::<TimestampNanosecondArray>()
+                .ok_or("`timestamp` must be Timestamp(Nanosecond)")?;
+            let prices = column("price")?
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .ok_or("`price` must be Float64")?;
+            let quantities = column("quantity")?
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .ok_or("`quantity` must be Float64")?;
+            let taker_sides = match batch.column_by_name("taker_side") {
+                Some(column) => Some(
+                    column
+                        .as_any()
+                        .downcast_ref::<StringArray>()
+                        .ok_or("`taker_side` must be Utf8")?,
+                ),
+                None => None,
+            };
+            for i in 0..batch.num_rows() {
+                if symbols.is_null(i) || timestamps.is_null(i) {
+                    continue;
+                }
+                store.insert(
+                    symbols.value(i),
+                    timestamps.value(i) as u64 / 1000,
+                    prices.value(i),
+                    quantities.value(i),
+                    taker_sides
+                        .filter(|sides| sides.is_valid(i))
+                        .and_then(|sides| Side::from_str_name(sides.value(i)))
+                        .unwrap_or_default(),
+                );
+            }
+        }
+        Ok(store)
+    }
+
+    fn slice(&self, symbol: &str, range: TimeRange) -> Trades {
+        let Some(trades) = self.trades.get(symbol) else {
+            return Trades::default();
+        };
+        let start = trades
+            .timestamps
+            .partition_point(|t| *t < range.start_micros);
+        let end = trades
+            .timestamps
+            .partition_point(|t| *t <= range.end_micros);
+        Trades {
+            timestamps: trades.timestamps[start..end].to_vec(),
+            prices: trades.prices[start..end].to_vec(),
+            quantities: trades.quantities[start..end].to_vec(),
+            taker_sides: trades.taker_sides[start..end].to_vec(),
+        }
+    }
+}
+
+#[async_trait]
+impl TradeStore for MemoryStore {
+    async fn trades(&self, symbol: &str, range: TimeRange) -> Result<Trades, Status> {
+        Ok(self.slice(symbol, range))
+    }
+
+    async fn watermark(&self, symbol: &str) -> Result<Option<u64>, Status> {
+        Ok(self
+            .trades
+            .get(symbol)
+            .and_then(|trades| trades.timestamps.last().copied()))
+    }
+
+    fn pushes_down(&self, _aggregation: Aggregation) -> bool {
+        true
+    }
+
+    async fn summary(&self, symbol: &str, range: TimeRange) -> Result<TradeSummary, Status> {
+        let trades = self.slice(symbol, range);
+        let mut summary = TradeSummary::default();
+        for (price, quantity) in trades.prices.iter().zip(&trades.quantities) {
+            summary.volume += quantity;
+            summary.quote_volume += price * quantity;
+            summary.trade_count += 1;
+        }
+        Ok(summary)
+    }
+
+    async fn candles(
+        &self,
+        symbol: &str,
+        range: TimeRange,
+        interval: Duration,
+    ) -> Result<Vec<Candle>, Status> {
+        let interval_micros = interval.as_micros() as u64;
+        if interval_micros == 0 {
+            return Err(Status::invalid_argument("interval must be positive"));
+        }
+        Ok(fold_candles(&self.slice(symbol, range), interval))
+    }
+
+    async fn symbols(&self) -> Result<Vec<String>, Status> {
+        Ok(self.trades.keys().cloned().collect())
+    }
+}
@@ -0,0 +1,268 @@
+pub mod clickhouse;
+pub mod memory;
+pub mod table;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use datafusion::catalog::TableProvider;
+use prost_types::Timestamp;
+use tonic::Status;
+
+use crate::data::trade::Side;
+
+/// Inclusive range of exchange timestamps, in microseconds since the epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeRange {
+    pub start_micros: u64,
+    pub end_micros: u64,
+}
+
+impl TimeRange {
+    pub fn from_request(
+        start: Option<&Timestamp>,
+        end: Option<&Timestamp>,
+    ) -> Result<Self, Status> {
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(Status::invalid_argument(
+                "start_timestamp and end_timestamp are required",
+            ));
+        };
+        let range = Self {
+            start_micros: to_micros(start),
+            end_micros: to_micros(end),
+        };
+        if range.start_micros > range.end_micros {
+            return Err(Status::invalid_argument(
+                "start_timestamp must not be after end_timestamp",
+            ));
+        }
+        Ok(range)
+    }
+
+    pub fn contains(&self, micros: u64) -> bool {
+        self.start_micros <= micros && micros <= self.end_micros
+    }
+}
+
+/// Microseconds since the epoch, clamping times before it to zero.
+pub fn to_micros(t: &Timestamp) -> u64 {
+    (t.seconds.max(0) as u64) * 1_000_000 + (t.nanos.max(0) as u64) / 1_000
+}
+
+/// Trades for one symbol in columnar form, ordered by timestamp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trades {
+    /// Microseconds since the epoch.
+    pub timestamps: Vec<u64>,
+    pub prices: Vec<f64>,
+    pub quantities: Vec<f64>,
+    pub taker_sides: Vec<Side>,
+}
+
+impl Trades {
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    pub fn push(&mut self, timestamp: u64, price: f64, quantity: f64, taker_side: Side) {
+        self.timestamps.push(timestamp);
+        self.prices.push(price);
+        self.quantities.push(quantity);
+        self.taker_sides.push(taker_side);
+    }
+}
+
+/// Totals over every trade in a range.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TradeSummary {
+    /// Base-asset volume.
+    pub volume: f64,
+    /// Sum of `price * quantity`.
+    pub quote_volume: f64,
+    pub trade_count: u64,
+}
+
+impl TradeSummary {
+    pub fn vwap(&self) -> f64 {
+        self.quote_volume / self.volume
+    }
+}
+
+/// An aggregation a store may compute itself instead of returning raw trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aggregation {
+    /// [`TradeStore::summary`].
+    Summary,
+    /// [`TradeStore::candles`].
+    Candles,
+}
+
+/// One OHLCV bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    /// Start of the bucket, in microseconds since the epoch.
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Base-asset volume.
+    pub volume: f64,
+    /// Sum of `price * quantity`.
+    pub quote_volume: f64,
+    pub trade_count: u64,
+    /// Base-asset volume taken by buyers and by sellers.
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+}
+
+impl Candle {
+    pub fn vwap(&self) -> f64 {
+        if self.volume == 0.0 {
+            self.close
+        } else {
+            self.quote_volume / self.volume
+        }
+    }
+}
+
+/// Candles of width `interval` over time-ordered `trades`.
+pub fn fold_candles(trades: &Trades, interval: Duration) -> Vec<Candle> {
+    let mut builder = CandleBuilder::new(interval, None);
+    let mut candles = Vec::new();
+    for i in 0..trades.len() {
+        candles.extend(builder.push(
+            trades.timestamps[i],
+            trades.prices[i],
+            trades.quantities[i],
+            trades.taker_sides[i],
+        ));
+    }
+    candles.extend(builder.current().cloned());
+    candles
+}
+
+/// Folds time-ordered trades into epoch-aligned candles.
+#[derive(Debug, Clone)]
+pub struct CandleBuilder {
+    interval_micros: u64,
+    current: Option<Candle>,
+}
+
+impl CandleBuilder {
+    /// Starts from `current`, the candle still open, if any.
+    pub fn new(interval: Duration, current: Option<Candle>) -> Self {
+        Self {
+            interval_micros: interval.as_micros() as u64,
+            current,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Candle> {
+        self.current.as_ref()
+    }
+
+    /// Adds a trade and returns the previous candle if the trade opened a new
+    /// one. Trades older than the open candle are dropped.
+    pub fn push(
+        &mut self,
+        timestamp: u64,
+        price: f64,
+        quantity: f64,
+        side: Side,
+    ) -> Option<Candle> {
+        let bucket = timestamp / self.interval_micros * self.interval_micros;
+        let (buy_volume, sell_volume) = match side {
+            Side::Buy => (quantity, 0.0),
+            Side::Sell => (0.0, quantity),
+            Side::Unknown => (0.0, 0.0),
+        };
+        if let Some(candle) = self.current.as_mut() {
+            if candle.timestamp > bucket {
+                return None;
+            }
+            if candle.timestamp == bucket {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.volume += quantity;
+                candle.quote_volume += price * quantity;
+                candle.trade_count += 1;
+                candle.buy_volume += buy_volume;
+                candle.sell_volume += sell_volume;
+                return None;
+            }
+        }
+        self.current.replace(Candle {
+            timestamp: bucket,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: quantity,
+            quote_volume: price * quantity,
+            trade_count: 1,
+            buy_volume,
+            sell_volume,
+        })
+    }
+}
+
+/// Read access to historical trades, so the RPC handlers don't depend on
+/// where trades are stored.
+///
+/// Only `trades` and `symbols` are required. A store that can aggregate
+/// where the trades live reports so through `pushes_down` and implements the
+/// matching method; otherwise [`crate::planner::Planner`] aggregates the raw
+/// trades in process, over `table`.
+#[async_trait]
+pub trait TradeStore: Send + Sync {
+    /// Every trade for `symbol` within `range`, ordered by timestamp.
+    async fn trades(&self, symbol: &str, range: TimeRange) -> Result<Trades, Status>;
+
+    /// Every symbol with at least one stored trade, sorted.
+    async fn symbols(&self) -> Result<Vec<String>, Status>;
+
+    /// The store's trades as a DataFusion table with
+    /// [`table::trades_schema`]. Without one, [`table::StoreTable`] reads them
+    /// through `trades`.
+    fn table(&self) -> Option<Arc<dyn TableProvider>> {
+        None
+    }
+
+    /// Exchange time, in microseconds, up to which `symbol`'s trades have
+    /// been ingested. `None` when unknown, which keeps results from being
+    /// cached.
+    async fn watermark(&self, _symbol: &str) -> Result<Option<u64>, Status> {
+        Ok(None)
+    }
+
+    /// Whether the store implements `aggregation` itself.
+    fn pushes_down(&self, _aggregation: Aggregation) -> bool {
+        false
+    }
+
+    /// Totals over the trades for `symbol` within `range`. Only called when
+    /// `pushes_down(Aggregation::Summary)`.
+    async fn summary(&self, _symbol: &str, _range: TimeRange) -> Result<TradeSummary, Status> {
+        Err(Status::unimplemented("store does not aggregate trades"))
+    }
+
+    /// Candles of width `interval` aligned to the epoch, ordered by timestamp.
+    /// Buckets without trades are omitted. Only called when
+    /// `pushes_down(Aggregation::Candles)`.
+    async fn candles(
+        &self,
+        _symbol: &str,
+        _range: TimeRange,
+        _interval: Duration,
+    ) -> Result<Vec<Candle>, Status> {
+        Err(Status::unimplemented("store does not build candles"))
+    }
+}
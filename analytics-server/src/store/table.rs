@@ -0,0 +1,543 @@
+//! Trades and candles as DataFusion tables, so analytics can be written as
+//! SQL over `trades` and `candles`. Conditions on `symbol` and the timestamp
+//! are applied by the store while scanning; DataFusion applies the rest.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use datafusion::arrow::array::{
+    ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array,
+};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use datafusion::catalog::{Session, TableProvider};
+use datafusion::datasource::TableType;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::expr::InList;
+use datafusion::logical_expr::{Between, BinaryExpr, Expr, Operator, TableProviderFilterPushDown};
+use datafusion::physical_expr::LexOrdering;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::{PartitionStream, StreamingTableExec};
+use datafusion::scalar::ScalarValue;
+
+use super::{Aggregation, Candle, TimeRange, TradeStore, Trades, fold_candles};
+
+const SYMBOL: &str = "symbol";
+const TIMESTAMP: &str = "exchange_timestamp";
+const CANDLE_TIMESTAMP: &str = "timestamp";
+
+/// Columns of the `trades` table. `exchange_timestamp` is in microseconds
+/// since the epoch.
+pub fn trades_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(SYMBOL, DataType::Utf8, false),
+        Field::new(TIMESTAMP, DataType::Int64, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("quantity", DataType::Float64, false),
+        Field::new("taker_side", DataType::Utf8, false),
+    ]))
+}
+
+/// Columns of the `candles` table. `timestamp` is the start of the candle,
+/// in microseconds since the epoch.
+pub fn candles_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(SYMBOL, DataType::Utf8, false),
+        Field::new(CANDLE_TIMESTAMP, DataType::Int64, false),
+        Field::new("open", DataType::Float64, false),
+        Field::new("high", DataType::Float64, false),
+        Field::new("low", DataType::Float64, false),
+        Field::new("close", DataType::Float64, false),
+        Field::new("volume", DataType::Float64, false),
+        Field::new("quote_volume", DataType::Float64, false),
+        Field::new("trade_count", DataType::UInt64, false),
+        Field::new("buy_volume", DataType::Float64, false),
+        Field::new("sell_volume", DataType::Float64, false),
+    ]))
+}
+
+/// One symbol's candles as a batch with [`candles_schema`].
+pub fn candles_batch(symbol: &str, candles: &[Candle]) -> Result<RecordBatch> {
+    let float = |value: fn(&Candle) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(candles.iter().map(value)))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(std::iter::repeat_n(
+            symbol,
+            candles.len(),
+        ))),
+        Arc::new(Int64Array::from_iter_values(
+            candles.iter().map(|c| c.timestamp as i64),
+        )),
+        float(|c| c.open),
+        float(|c| c.high),
+        float(|c| c.low),
+        float(|c| c.close),
+        float(|c| c.volume),
+        float(|c| c.quote_volume),
+        Arc::new(UInt64Array::from_iter_values(
+            candles.iter().map(|c| c.trade_count),
+        )),
+        float(|c| c.buy_volume),
+        float(|c| c.sell_volume),
+    ];
+    Ok(RecordBatch::try_new(candles_schema(), columns)?)
+}
+
+/// One symbol's trades as a batch with [`trades_schema`].
+pub fn trades_batch(symbol: &str, trades: Trades) -> Result<RecordBatch> {
+    let len = trades.len();
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(std::iter::repeat_n(
+            symbol, len,
+        ))),
+        Arc::new(Int64Array::from_iter_values(
+            trades.timestamps.iter().map(|t| *t as i64),
+        )),
+        Arc::new(Float64Array::from(trades.prices)),
+        Arc::new(Float64Array::from(trades.quantities)),
+        Arc::new(StringArray::from_iter_values(
+            trades.taker_sides.iter().map(|side| side.as_str_name()),
+        )),
+    ];
+    Ok(RecordBatch::try_new(trades_schema(), columns)?)
+}
+
+/// The conditions on `symbol` and `exchange_timestamp` among a scan's filters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeFilter {
+    /// Symbols to keep; `None` keeps every symbol.
+    pub symbols: Option<Vec<String>>,
+    /// Inclusive bounds on `exchange_timestamp`.
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Default for TradeFilter {
+    fn default() -> Self {
+        Self {
+            symbols: None,
+            start: i64::MIN,
+            end: i64::MAX,
+        }
+    }
+}
+
+impl TradeFilter {
+    pub fn new(filters: &[Expr]) -> Self {
+        Self::on(TIMESTAMP, filters)
+    }
+
+    /// Like [`TradeFilter::new`], reading the time bounds from `time_column`.
+    pub fn on(time_column: &str, filters: &[Expr]) -> Self {
+        let mut filter = Self::default();
+        for expr in filters {
+            filter.apply(time_column, expr);
+        }
+        filter
+    }
+
+    /// Whether `expr` is one of the conditions a store applies exactly.
+    pub fn supports(expr: &Expr) -> bool {
+        Self::supports_on(TIMESTAMP, expr)
+    }
+
+    pub fn supports_on(time_column: &str, expr: &Expr) -> bool {
+        Self::default().apply(time_column, expr)
+    }
+
+    /// Pushdown support for each of `filters`.
+    pub fn pushdown(filters: &[&Expr]) -> Vec<TableProviderFilterPushDown> {
+        filters
+            .iter()
+            .map(|expr| {
+                if Self::supports(expr) {
+                    TableProviderFilterPushDown::Exact
+                } else {
+                    TableProviderFilterPushDown::Unsupported
+                }
+            })
+            .collect()
+    }
+
+    /// The timestamps to scan, or `None` if no trade can match.
+    pub fn range(&self) -> Option<TimeRange> {
+        if self.symbols.as_ref().is_some_and(Vec::is_empty) || self.end < 0 || self.start > self.end
+        {
+            return None;
+        }
+        Some(TimeRange {
+            start_micros: self.start.max(0) as u64,
+            end_micros: self.end as u64,
+        })
+    }
+
+    /// Narrows the filter by `expr`, returning false and leaving it unchanged
+    /// if `expr` is not a condition it understands.
+    fn apply(&mut self, time_column: &str, expr: &Expr) -> bool {
+        match expr {
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let (column, op, value) = match (column_name(left), column_name(right)) {
+                    (Some(column), None) => (column, *op, right.as_ref()),
+                    (None, Some(column)) => match op.swap() {
+                        Some(op) => (column, op, left.as_ref()),
+                        None => return false,
+                    },
+                    _ => return false,
+                };
+                match (column, op) {
+                    (SYMBOL, Operator::Eq) => match string_literal(value) {
+                        Some(symbol) => {
+                            self.keep_symbols(vec![symbol.to_string()]);
+                            true
+                        }
+                        None => false,
+                    },
+                    (column, op) if column == time_column => {
+                        let Some(value) = int_literal(value) else {
+                            return false;
+                        };
+                        match op {
+                            Operator::Eq => self.keep_between(value, value),
+                            Operator::Gt => self.keep_between(value.saturating_add(1), i64::MAX),
+                            Operator::GtEq => self.keep_between(value, i64::MAX),
+                            Operator::Lt => self.keep_between(i64::MIN, value.saturating_sub(1)),
+                            Operator::LtEq => self.keep_between(i64::MIN, value),
+                            _ => return false,
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Expr::Between(Between {
+                expr,
+                negated: false,
+                low,
+                high,
+            }) if column_name(expr) == Some(time_column) => {
+                match (int_literal(low), int_literal(high)) {
+                    (Some(low), Some(high)) => {
+                        self.keep_between(low, high);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Expr::InList(InList {
+                expr,
+                list,
+                negated: false,
+            }) if column_name(expr) == Some(SYMBOL) => {
+                let symbols: Option<Vec<String>> = list
+                    .iter()
+                    .map(|value| string_literal(value).map(str::to_string))
+                    .collect();
+                match symbols {
+                    Some(symbols) => {
+                        self.keep_symbols(symbols);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn keep_symbols(&mut self, symbols: Vec<String>) {
+        self.symbols = Some(match self.symbols.take() {
+            Some(kept) => kept.into_iter().filter(|s| symbols.contains(s)).collect(),
+            None => symbols,
+        });
+    }
+
+    fn keep_between(&mut self, start: i64, end: i64) {
+        self.start = self.start.max(start);
+        self.end = self.end.min(end);
+    }
+}
+
+fn column_name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Column(column) => Some(column.name.as_str()),
+        _ => None,
+    }
+}
+
+fn string_literal(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Literal(
+            ScalarValue::Utf8(Some(value))
+            | ScalarValue::LargeUtf8(Some(value))
+            | ScalarValue::Utf8View(Some(value)),
+            _,
+        ) => Some(value),
+        _ => None,
+    }
+}
+
+fn int_literal(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Literal(value, _) => match value {
+            ScalarValue::Int8(Some(v)) => Some(*v as i64),
+            ScalarValue::Int16(Some(v)) => Some(*v as i64),
+            ScalarValue::Int32(Some(v)) => Some(*v as i64),
+            ScalarValue::Int64(Some(v)) => Some(*v),
+            ScalarValue::UInt8(Some(v)) => Some(*v as i64),
+            ScalarValue::UInt16(Some(v)) => Some(*v as i64),
+            ScalarValue::UInt32(Some(v)) => Some(*v as i64),
+            ScalarValue::UInt64(Some(v)) => i64::try_from(*v).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `trades` for a store without a table of its own, read through
+/// [`TradeStore::trades`] one symbol at a time.
+pub struct StoreTable {
+    store: Arc<dyn TradeStore>,
+}
+
+impl StoreTable {
+    pub fn new(store: Arc<dyn TradeStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl fmt::Debug for StoreTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreTable").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl TableProvider for StoreTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        trades_schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(TradeFilter::pushdown(filters))
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let filter = TradeFilter::new(filters);
+        let scan = StoreScan {
+            store: self.store.clone(),
+            schema: trades_schema(),
+            rows: Rows::Trades,
+            range: filter.range(),
+            symbols: filter.symbols,
+        };
+        scan.plan(projection, limit)
+    }
+}
+
+/// `candles` of one fixed width for every symbol, from
+/// [`TradeStore::candles`] when the store builds them and folded from its
+/// trades otherwise. Conditions on `timestamp` are widened to whole candles
+/// while scanning, so DataFusion applies them again.
+pub struct CandleTable {
+    store: Arc<dyn TradeStore>,
+    interval: Duration,
+}
+
+impl CandleTable {
+    /// Panics if `interval` is under a microsecond.
+    pub fn new(store: Arc<dyn TradeStore>, interval: Duration) -> Self {
+        assert!(interval.as_micros() > 0, "candle interval must be positive");
+        Self { store, interval }
+    }
+}
+
+impl fmt::Debug for CandleTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CandleTable")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl TableProvider for CandleTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        candles_schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(filters
+            .iter()
+            .map(|expr| {
+                if TradeFilter::supports_on(CANDLE_TIMESTAMP, expr) {
+                    TableProviderFilterPushDown::Inexact
+                } else {
+                    TableProviderFilterPushDown::Unsupported
+                }
+            })
+            .collect())
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let filter = TradeFilter::on(CANDLE_TIMESTAMP, filters);
+        let width = self.interval.as_micros() as u64;
+        let range = filter.range().map(|range| TimeRange {
+            start_micros: range.start_micros / width * width,
+            end_micros: (range.end_micros / width * width).saturating_add(width - 1),
+        });
+        let scan = StoreScan {
+            store: self.store.clone(),
+            schema: candles_schema(),
+            rows: Rows::Candles(self.interval),
+            symbols: filter.symbols,
+            range,
+        };
+        scan.plan(projection, limit)
+    }
+}
+
+/// What a [`StoreScan`] reads for each symbol.
+#[derive(Debug, Clone, Copy)]
+enum Rows {
+    Trades,
+    /// Candles of this width.
+    Candles(Duration),
+}
+
+/// One scan of [`StoreTable`] or [`CandleTable`]. Symbols are read one at a
+/// time as the plan pulls batches, so a single symbol's rows are held at
+/// once and reading stops as soon as a limit is met.
+#[derive(Clone)]
+struct StoreScan {
+    store: Arc<dyn TradeStore>,
+    /// [`trades_schema`] or [`candles_schema`], as `rows` calls for.
+    schema: SchemaRef,
+    rows: Rows,
+    /// Symbols to read; `None` reads every symbol in the store.
+    symbols: Option<Vec<String>>,
+    /// `None` if no row can match.
+    range: Option<TimeRange>,
+}
+
+impl fmt::Debug for StoreScan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoreScan")
+            .field("rows", &self.rows)
+            .field("symbols", &self.symbols)
+            .field("range", &self.range)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StoreScan {
+    fn plan(
+        self,
+        projection: Option<&Vec<usize>>,
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(Arc::new(StreamingTableExec::try_new(
+            self.schema.clone(),
+            vec![Arc::new(self)],
+            projection,
+            Vec::<LexOrdering>::new(),
+            false,
+            limit,
+        )?))
+    }
+
+    /// `symbol`'s rows within `range`, or `None` if it has none.
+    async fn batch(&self, symbol: &str, range: TimeRange) -> Result<Option<RecordBatch>> {
+        let batch = match self.rows {
+            Rows::Trades => {
+                let trades = self.store.trades(symbol, range).await.map_err(external)?;
+                if trades.is_empty() {
+                    return Ok(None);
+                }
+                trades_batch(symbol, trades)?
+            }
+            Rows::Candles(interval) => {
+                let candles = if self.store.pushes_down(Aggregation::Candles) {
+                    self.store
+                        .candles(symbol, range, interval)
+                        .await
+                        .map_err(external)?
+                } else {
+                    let trades = self.store.trades(symbol, range).await.map_err(external)?;
+                    fold_candles(&trades, interval)
+                };
+                if candles.is_empty() {
+                    return Ok(None);
+                }
+                candles_batch(symbol, &candles)?
+            }
+        };
+        Ok(Some(batch))
+    }
+}
+
+impl PartitionStream for StoreScan {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let scan = self.clone();
+        let batches = async_stream::try_stream! {
+            if let Some(range) = scan.range {
+                let symbols = match &scan.symbols {
+                    Some(symbols) => symbols.clone(),
+                    None => scan.store.symbols().await.map_err(external)?,
+                };
+                for symbol in symbols {
+                    if let Some(batch) = scan.batch(&symbol, range).await? {
+                        yield batch;
+                    }
+                }
+            }
+        };
+        Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), batches))
+    }
+}
+
+pub(crate) fn external(error: impl std::error::Error + Send + Sync + 'static) -> DataFusionError {
+    DataFusionError::External(Box::new(error))
+}
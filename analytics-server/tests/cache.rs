@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use analytics_server::analytics::GetTradeAnalyticsResponse;
+use analytics_server::cache::{CacheConfig, CacheKey, ResultCache};
+use analytics_server::metrics::ServerMetrics;
+use analytics_server::store::TimeRange;
+use tonic::Status;
+
+/// Encoded size of [`response`] for counts below 128.
+const ENTRY_BYTES: usize = 20;
+
+fn cache(config: CacheConfig) -> (ResultCache, Arc<ServerMetrics>) {
+    let metrics = Arc::new(ServerMetrics::new().unwrap());
+    (ResultCache::new(config, metrics.clone()), metrics)
+}
+
+fn config() -> CacheConfig {
+    CacheConfig {
+        capacity: 2 * ENTRY_BYTES,
+        max_entry: ENTRY_BYTES,
+        watermark_lag: Duration::ZERO,
+        ttl: Duration::from_secs(60),
+    }
+}
+
+fn key(symbol: &str) -> CacheKey {
+    CacheKey {
+        rpc: "GetTradeAnalytics",
+        symbol: symbol.to_string(),
+        range: TimeRange {
+            start_micros: 0,
+            end_micros: 10,
+        },
+        params: String::new(),
+    }
+}
+
+fn response(trades_count: u64) -> GetTradeAnalyticsResponse {
+    GetTradeAnalyticsResponse {
+        total_volume_in_quotes: 1.5,
+        vwap: 2.5,
+        trades_count,
+    }
+}
+
+/// The trade count `cache` answers with when a fresh computation would give
+/// `trades_count`.
+async fn fetch(
+    cache: &ResultCache,
+    key: CacheKey,
+    watermark: Option<u64>,
+    trades_count: u64,
+) -> u64 {
+    cache
+        .get_or_compute(key, async { Ok(watermark) }, async {
+            Ok(response(trades_count))
+        })
+        .await
+        .unwrap()
+        .trades_count
+}
+
+fn requests(metrics: &ServerMetrics, result: &str) -> u64 {
+    metrics
+        .cache_requests
+        .with_label_values(&["GetTradeAnalytics", result])
+        .get()
+}
+
+#[tokio::test]
+async fn closed_windows_are_cached() {
+    let (cache, metrics) = cache(config());
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 1).await, 1);
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 2).await, 1);
+    assert_eq!(fetch(&cache, key("ETHUSDT"), Some(11), 3).await, 3);
+    assert_eq!(requests(&metrics, "miss"), 2);
+    assert_eq!(requests(&metrics, "hit"), 1);
+    assert_eq!(metrics.cache_entries.get(), 2);
+    assert_eq!(metrics.cache_bytes.get(), 2 * ENTRY_BYTES as i64);
+}
+
+#[tokio::test]
+async fn open_windows_are_not_cached() {
+    let (cache, metrics) = cache(CacheConfig {
+        watermark_lag: Duration::from_micros(5),
+        ..config()
+    });
+    // The window ends at 10: only a watermark past 15 closes it.
+    for watermark in [None, Some(10), Some(15)] {
+        assert_eq!(fetch(&cache, key("BTCUSDT"), watermark, 1).await, 1);
+        assert_eq!(fetch(&cache, key("BTCUSDT"), watermark, 2).await, 2);
+    }
+    assert_eq!(requests(&metrics, "bypass"), 6);
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(16), 3).await, 3);
+    assert_eq!(fetch(&cache, key("BTCUSDT"), None, 4).await, 3);
+}
+
+#[tokio::test]
+async fn expired_entries_are_recomputed() {
+    let (cache, metrics) = cache(CacheConfig {
+        ttl: Duration::ZERO,
+        ..config()
+    });
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 1).await, 1);
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 2).await, 2);
+    assert_eq!(requests(&metrics, "miss"), 2);
+    assert_eq!(requests(&metrics, "hit"), 0);
+}
+
+#[tokio::test]
+async fn least_recently_used_entries_are_evicted() {
+    let (cache, metrics) = cache(config());
+    fetch(&cache, key("BTCUSDT"), Some(11), 1).await;
+    fetch(&cache, key("ETHUSDT"), Some(11), 2).await;
+    // Reading BTCUSDT leaves ETHUSDT as the least recently used.
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 0).await, 1);
+    fetch(&cache, key("SOLUSDT"), Some(11), 3).await;
+    assert_eq!(metrics.cache_evictions.get(), 1);
+
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 0).await, 1);
+    assert_eq!(fetch(&cache, key("SOLUSDT"), Some(11), 0).await, 3);
+    assert_eq!(fetch(&cache, key("ETHUSDT"), Some(11), 4).await, 4);
+}
+
+#[tokio::test]
+async fn oversized_results_are_not_cached() {
+    let (cache, metrics) = cache(CacheConfig {
+        max_entry: ENTRY_BYTES - 1,
+        ..config()
+    });
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 1).await, 1);
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 2).await, 2);
+    assert_eq!(metrics.cache_oversized.get(), 2);
+    assert_eq!(metrics.cache_entries.get(), 0);
+}
+
+#[tokio::test]
+async fn errors_are_not_cached() {
+    let (cache, _) = cache(config());
+    let error = cache
+        .get_or_compute(key("BTCUSDT"), async { Ok(Some(11)) }, async {
+            Err::<GetTradeAnalyticsResponse, _>(Status::not_found("no trades"))
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), tonic::Code::NotFound);
+    assert_eq!(fetch(&cache, key("BTCUSDT"), Some(11), 1).await, 1);
+}
@@ -0,0 +1,109 @@
+"""Writes the MACD golden files used by tests/indicators.rs.
+
+Each file starts with a `# source:` line naming what computed it, then has
+one row per input value: the close and, once warmed up, the MACD line, signal
+and histogram. The outputs come from talib.MACD, and the script fails when
+TA-Lib's Python bindings are missing. With --port they come from `ta_macd`
+below instead, a line-by-line port of TA-Lib's TA_MACD (ta_MACD.c) with the
+default EMA compatibility.
+
+    python3 tests/data/generate_macd.py [--port]
+"""
+
+import argparse
+import csv
+import os
+import random
+import sys
+
+PERIODS = [(12, 26, 9), (5, 13, 4), (3, 10, 16)]
+LENGTH = 120
+
+
+def closes():
+    rng = random.Random(42)
+    price = 100.0
+    values = []
+    for _ in range(LENGTH):
+        price = max(1.0, price + rng.gauss(0.0, 1.5))
+        values.append(round(price, 2))
+    return values
+
+
+def ta_int_ema(values, start, period, k):
+    """TA_INT_EMA: seeded with the SMA of the `period` values ending at `start`."""
+    today = start - (period - 1)
+    prev = sum(values[today : today + period]) / period
+    today += period
+    out = [prev]
+    while today < len(values):
+        prev = (values[today] - prev) * k + prev
+        today += 1
+        out.append(prev)
+    return out
+
+
+def ta_macd(values, fast, slow, signal):
+    if slow < fast:
+        fast, slow = slow, fast
+    start = slow - 1
+    slow_ema = ta_int_ema(values, start, slow, 2.0 / (slow + 1))
+    fast_ema = ta_int_ema(values, start, fast, 2.0 / (fast + 1))
+    line = [f - s for f, s in zip(fast_ema, slow_ema)]
+    if len(line) < signal:
+        return [], [], []
+    signal_line = ta_int_ema(line, signal - 1, signal, 2.0 / (signal + 1))
+    line = line[signal - 1 :]
+    return line, signal_line, [m - s for m, s in zip(line, signal_line)]
+
+
+def talib_macd():
+    """talib.MACD as a function of plain lists, and the source it records."""
+    try:
+        import numpy
+        import talib
+    except ImportError:
+        sys.exit("TA-Lib's Python bindings are not installed; pass --port to use ta_macd")
+
+    def macd(values, fast, slow, signal):
+        out = talib.MACD(numpy.array(values), fast, slow, signal)
+        return tuple([v for v in series if v == v] for series in out)
+
+    return macd, f"talib {talib.__version__} MACD"
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument(
+        "--port",
+        action="store_true",
+        help="compute with ta_macd, this script's port of TA_MACD, instead of TA-Lib",
+    )
+    args = parser.parse_args()
+    if args.port:
+        macd, source = ta_macd, "ta_macd, generate_macd.py's port of TA-Lib's TA_MACD"
+    else:
+        macd, source = talib_macd()
+
+    values = closes()
+    directory = os.path.dirname(os.path.abspath(__file__))
+    for fast, slow, signal in PERIODS:
+        line, signal_line, histogram = macd(values, fast, slow, signal)
+        warm_up = len(values) - len(line)
+        path = os.path.join(directory, f"macd_{fast}_{slow}_{signal}.csv")
+        with open(path, "w", newline="") as f:
+            f.write(f"# source: {source}\n")
+            writer = csv.writer(f, lineterminator="\n")
+            writer.writerow(["close", "macd", "signal", "histogram"])
+            for i, close in enumerate(values):
+                if i < warm_up:
+                    writer.writerow([close, "", "", ""])
+                else:
+                    j = i - warm_up
+                    writer.writerow(
+                        [close] + [repr(v) for v in (line[j], signal_line[j], histogram[j])]
+                    )
+
+
+if __name__ == "__main__":
+    main()
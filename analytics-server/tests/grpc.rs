@@ -0,0 +1,1578 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use analytics_server::analytics::analytics_service_client::AnalyticsServiceClient;
+use analytics_server::analytics::analytics_service_server::AnalyticsServiceServer;
+use analytics_server::analytics::{
+    ComputeIndicatorRequest, ComputeIndicatorResponse, ExecuteQueryRequest, GetAtrRequest,
+    GetBandsResponse, GetBollingerBandsRequest, GetCandlesRequest, GetKeltnerChannelsRequest,
+    GetMacdRequest, GetMovingAverageRequest, GetMovingAverageResponse, GetTradeAnalyticsRequest,
+    IndicatorInput, IndicatorUpdate, ListIndicatorsRequest, SeriesType,
+    SubscribeToIndicatorRequest, SubscribeToTradesRequest,
+};
+use analytics_server::arrow_flight::flight_descriptor::DescriptorType;
+use analytics_server::arrow_flight::flight_service_client::FlightServiceClient;
+use analytics_server::arrow_flight::flight_service_server::FlightServiceServer;
+use analytics_server::arrow_flight::{Criteria, FlightData, FlightDescriptor, Ticket};
+use analytics_server::cache::CacheConfig;
+use analytics_server::data;
+use analytics_server::feed::MemoryFeed;
+use analytics_server::flight::AnalyticsFlightService;
+use analytics_server::service::AnalyticsServiceHandler;
+use analytics_server::store::memory::MemoryStore;
+use datafusion::arrow::array::{
+    AsArray, Float64Array, RecordBatch, StringArray, TimestampNanosecondArray,
+};
+use datafusion::arrow::compute::concat_batches;
+use datafusion::arrow::datatypes::{Float64Type, Int64Type, UInt64Type};
+use datafusion::arrow::ipc::reader::StreamReader;
+use datafusion::arrow::ipc::writer::FileWriter;
+use futures::StreamExt;
+use prost_types::Timestamp;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::Code;
+use tonic::transport::{Channel, Server};
+
+const SYMBOL: &str = "BTCUSDT";
+const START_SECONDS: i64 = 1_700_000_000;
+/// 2100-01-01, safely after any subscription's seeding cutoff.
+const LIVE_SECONDS: i64 = 4_102_444_800;
+
+/// Ten BTCUSDT trades one second apart with prices 100..=109 and quantities
+/// alternating between 1 (buyer-initiated) and 2 (seller-initiated), plus a
+/// single ETHUSDT trade.
+fn fixture() -> Vec<data::Trade> {
+    let mut trades: Vec<data::Trade> = (0..10)
+        .map(|i| data::Trade {
+            symbol: SYMBOL.to_string(),
+            price: 100.0 + i as f64,
+            quantity: if i % 2 == 0 { 1.0 } else { 2.0 },
+            exchange: data::trade::Exchange::Binance as i32,
+            exchange_timestamp: micros(i),
+            ingestion_timestamp: None,
+            trade_id: i.to_string(),
+            taker_side: if i % 2 == 0 {
+                data::trade::Side::Buy as i32
+            } else {
+                data::trade::Side::Sell as i32
+            },
+        })
+        .collect();
+    trades.push(data::Trade {
+        symbol: "ETHUSDT".to_string(),
+        price: 2000.0,
+        quantity: 3.0,
+        exchange: data::trade::Exchange::Coinbase as i32,
+        exchange_timestamp: micros(0),
+        ingestion_timestamp: None,
+        trade_id: "eth-0".to_string(),
+        taker_side: data::trade::Side::Unknown as i32,
+    });
+    trades
+}
+
+fn micros(offset_seconds: i64) -> u64 {
+    ((START_SECONDS + offset_seconds) * 1_000_000) as u64
+}
+
+fn timestamp(offset_seconds: i64) -> Option<Timestamp> {
+    Some(Timestamp {
+        seconds: START_SECONDS + offset_seconds,
+        nanos: 0,
+    })
+}
+
+fn store(trades: &[data::Trade]) -> MemoryStore {
+    let mut store = MemoryStore::new();
+    for trade in trades {
+        store.insert(
+            &trade.symbol,
+            trade.exchange_timestamp,
+            trade.price,
+            trade.quantity,
+            trade.taker_side(),
+        );
+    }
+    store
+}
+
+async fn serve(store: MemoryStore, feed: MemoryFeed) -> AnalyticsServiceClient<Channel> {
+    serve_handler(AnalyticsServiceHandler::new(
+        Arc::new(store),
+        Arc::new(feed),
+    ))
+    .await
+}
+
+async fn serve_handler(handler: AnalyticsServiceHandler) -> AnalyticsServiceClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(AnalyticsServiceServer::new(handler))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    AnalyticsServiceClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap()
+}
+
+async fn client() -> AnalyticsServiceClient<Channel> {
+    let trades = fixture();
+    serve(store(&trades), MemoryFeed::new(trades)).await
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {expected}, got {actual}"
+    );
+}
+
+fn moving_average_request(window_size: u32, end_offset: i64) -> GetMovingAverageRequest {
+    GetMovingAverageRequest {
+        symbol: SYMBOL.to_string(),
+        start_timestamp: timestamp(0),
+        end_timestamp: timestamp(end_offset),
+        window_size,
+        interval: None,
+        series_type: SeriesType::Close as i32,
+        page_size: 0,
+        page_token: String::new(),
+        max_points: 0,
+    }
+}
+
+fn seconds(seconds: i64) -> Option<prost_types::Duration> {
+    Some(prost_types::Duration { seconds, nanos: 0 })
+}
+
+#[tokio::test]
+async fn trade_analytics_over_range() {
+    let mut client = client().await;
+    let response = client
+        .get_trade_analytics(GetTradeAnalyticsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(4),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    // 100*1 + 101*2 + 102*1 + 103*2 + 104*1 over 7 units.
+    assert_close(response.total_volume_in_quotes, 714.0);
+    assert_close(response.vwap, 102.0);
+    assert_eq!(response.trades_count, 5);
+}
+
+#[tokio::test]
+async fn trade_analytics_unknown_symbol_is_not_found() {
+    let mut client = client().await;
+    let status = client
+        .get_trade_analytics(GetTradeAnalyticsRequest {
+            symbol: "DOGEUSDT".to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn trade_analytics_requires_a_range() {
+    let mut client = client().await;
+    let status = client
+        .get_trade_analytics(GetTradeAnalyticsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: None,
+            end_timestamp: timestamp(9),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn moving_average_includes_partial_windows() {
+    let mut client = client().await;
+    let points = client
+        .get_moving_average(moving_average_request(3, 4))
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+    assert_eq!(values, vec![100.0, 100.5, 101.0, 102.0, 103.0]);
+    let timestamps: Vec<u64> = points.iter().map(|p| p.timestamp).collect();
+    assert_eq!(timestamps, (0..5).map(micros).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn moving_average_over_candle_closes_reports_full_windows() {
+    let mut client = client().await;
+    let points = client
+        .get_moving_average(GetMovingAverageRequest {
+            interval: seconds(5),
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // 5s candles close at 104 and 109.
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].timestamp, micros(5));
+    assert_close(points[0].value, 106.5);
+}
+
+#[tokio::test]
+async fn moving_average_over_candle_opens() {
+    let mut client = client().await;
+    let points = client
+        .get_moving_average(GetMovingAverageRequest {
+            interval: seconds(5),
+            series_type: SeriesType::Open as i32,
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    assert_eq!(points.len(), 1);
+    assert_close(points[0].value, 102.5);
+}
+
+#[tokio::test]
+async fn moving_average_needs_a_full_window_of_candles() {
+    let mut client = client().await;
+    let status = client
+        .get_moving_average(GetMovingAverageRequest {
+            interval: seconds(5),
+            ..moving_average_request(3, 9)
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(status.message(), "Not enough data to compute SMA");
+}
+
+#[tokio::test]
+async fn ema_is_seeded_with_the_first_window_sma() {
+    let mut client = client().await;
+    let points = client
+        .get_ema(moving_average_request(3, 4))
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // Seed (100 + 101 + 102) / 3, then a multiplier of 2 / (3 + 1).
+    let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+    assert_eq!(values, vec![101.0, 102.0, 103.0]);
+    let timestamps: Vec<u64> = points.iter().map(|p| p.timestamp).collect();
+    assert_eq!(timestamps, vec![micros(2), micros(3), micros(4)]);
+}
+
+#[tokio::test]
+async fn ema_over_candle_highs() {
+    let mut client = client().await;
+    let points = client
+        .get_ema(GetMovingAverageRequest {
+            interval: seconds(2),
+            series_type: SeriesType::High as i32,
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // 2s candle highs are 101, 103, 105, 107 and 109; each step moves the
+    // 2-period EMA two thirds of the way to the new high.
+    let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+    assert_eq!(values.len(), 4);
+    assert_close(values[0], 102.0);
+    assert_close(values[1], 102.0 / 3.0 + 105.0 * 2.0 / 3.0);
+    assert_eq!(points[0].timestamp, micros(2));
+}
+
+#[tokio::test]
+async fn ema_without_enough_trades_is_rejected() {
+    let mut client = client().await;
+    let status = client
+        .get_ema(moving_average_request(20, 9))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(status.message(), "Not enough data to compute EMA");
+}
+
+#[tokio::test]
+async fn rsi_uses_wilder_smoothing() {
+    let mut store = MemoryStore::new();
+    for (i, price) in [1.0, 2.0, 1.0, 2.0, 1.0].into_iter().enumerate() {
+        store.insert(SYMBOL, micros(i as i64), price, 1.0, data::trade::Side::Buy);
+    }
+    let mut client = serve(store, MemoryFeed::default()).await;
+    let points = client
+        .get_rsi(moving_average_request(2, 4))
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // Seeded with a gain and a loss of 0.5 each; then gains of 0.75 vs losses
+    // of 0.25, then 0.375 vs 0.625.
+    let values: Vec<f64> = points.iter().map(|p| p.value).collect();
+    assert_eq!(values.len(), 3);
+    assert_close(values[0], 50.0);
+    assert_close(values[1], 75.0);
+    assert_close(values[2], 37.5);
+    assert_eq!(points[0].timestamp, micros(2));
+}
+
+#[tokio::test]
+async fn rsi_of_a_steady_rise_is_100() {
+    let mut client = client().await;
+    let points = client
+        .get_rsi(moving_average_request(3, 9))
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    assert_eq!(points.len(), 7);
+    for point in &points {
+        assert_close(point.value, 100.0);
+    }
+}
+
+#[tokio::test]
+async fn rsi_without_enough_trades_is_rejected() {
+    let mut client = client().await;
+    let status = client
+        .get_rsi(moving_average_request(10, 9))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(status.message(), "Not enough data to compute RSI");
+}
+
+#[tokio::test]
+async fn macd_over_a_linear_trend() {
+    let mut client = client().await;
+    let points = client
+        .get_macd(GetMacdRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            fast_period: 2,
+            slow_period: 3,
+            signal_period: 2,
+            interval: None,
+            series_type: SeriesType::Close as i32,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // On prices rising by 1 per trade an N-period EMA lags by (N - 1) / 2, so
+    // the 2/3 MACD line settles at 1 - 0.5 and its signal line matches it.
+    assert_eq!(points.len(), 7);
+    assert_eq!(points[0].timestamp, micros(3));
+    for point in &points {
+        assert_close(point.macd_line, 0.5);
+        assert_close(point.signal_line, 0.5);
+        assert_close(point.histogram, 0.0);
+    }
+}
+
+#[tokio::test]
+async fn macd_without_enough_trades_is_rejected() {
+    let mut client = client().await;
+    let status = client
+        .get_macd(GetMacdRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            fast_period: 12,
+            slow_period: 26,
+            signal_period: 9,
+            interval: None,
+            series_type: SeriesType::Close as i32,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn macd_rejects_invalid_periods() {
+    let mut client = client().await;
+    let cases = [
+        (0, 3, 2, "MACD periods must be positive"),
+        (2, 3, 0, "MACD periods must be positive"),
+        (3, 3, 2, "fast_period must be less than slow_period"),
+        (5, 3, 2, "fast_period must be less than slow_period"),
+    ];
+    for (fast_period, slow_period, signal_period, message) in cases {
+        let status = client
+            .get_macd(GetMacdRequest {
+                symbol: SYMBOL.to_string(),
+                start_timestamp: timestamp(0),
+                end_timestamp: timestamp(9),
+                fast_period,
+                slow_period,
+                signal_period,
+                interval: None,
+                series_type: SeriesType::Close as i32,
+                page_size: 0,
+                page_token: String::new(),
+                max_points: 0,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), message);
+    }
+}
+
+#[tokio::test]
+async fn moving_average_pages_follow_the_token() {
+    let mut client = client().await;
+    let all = client
+        .get_moving_average(moving_average_request(2, 9))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(all.next_page_token.is_empty());
+
+    let mut pages = Vec::new();
+    let mut page_token = String::new();
+    loop {
+        let page = client
+            .get_moving_average(GetMovingAverageRequest {
+                page_size: 3,
+                page_token,
+                ..moving_average_request(2, 9)
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        pages.push(page.points.len());
+        page_token = page.next_page_token;
+        if page_token.is_empty() {
+            break;
+        }
+    }
+    assert_eq!(pages, vec![3, 3, 3, 1]);
+}
+
+#[tokio::test]
+async fn moving_average_rejects_a_bad_page_token() {
+    let mut client = client().await;
+    for page_token in ["abc", "11", "0-1-1", "zz-1700000003000000-1"] {
+        let status = client
+            .get_moving_average(GetMovingAverageRequest {
+                page_token: page_token.to_string(),
+                ..moving_average_request(2, 9)
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+}
+
+#[tokio::test]
+async fn page_tokens_only_continue_the_request_they_came_from() {
+    let mut client = client().await;
+    let page_token = client
+        .get_moving_average(GetMovingAverageRequest {
+            page_size: 3,
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .next_page_token;
+
+    let status = client
+        .get_moving_average(GetMovingAverageRequest {
+            page_size: 3,
+            page_token: page_token.clone(),
+            ..moving_average_request(3, 9)
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "page_token was issued for a different request"
+    );
+    let status = client
+        .get_ema(GetMovingAverageRequest {
+            page_size: 3,
+            page_token: page_token.clone(),
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    // The page size may change between pages.
+    let rest = client
+        .get_moving_average(GetMovingAverageRequest {
+            page_size: 0,
+            page_token,
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(rest.points.len(), 7);
+    assert!(rest.next_page_token.is_empty());
+}
+
+#[tokio::test]
+async fn stream_moving_average_sends_chunks() {
+    let mut client = client().await;
+    let all = client
+        .get_moving_average(moving_average_request(2, 9))
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+    let chunks: Vec<GetMovingAverageResponse> = client
+        .stream_moving_average(GetMovingAverageRequest {
+            page_size: 4,
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    let sizes: Vec<usize> = chunks.iter().map(|c| c.points.len()).collect();
+    assert_eq!(sizes, vec![4, 4, 2]);
+    assert!(chunks[2].next_page_token.is_empty());
+
+    // A chunk's token resumes the unary call after it.
+    let resumed = client
+        .get_moving_average(GetMovingAverageRequest {
+            page_size: 4,
+            page_token: chunks[0].next_page_token.clone(),
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resumed.points, chunks[1].points);
+    assert_eq!(resumed.next_page_token, chunks[1].next_page_token);
+
+    let streamed: Vec<_> = chunks.into_iter().flat_map(|c| c.points).collect();
+    assert_eq!(streamed, all);
+}
+
+#[tokio::test]
+async fn max_points_downsamples_keeping_the_ends() {
+    let mut client = client().await;
+    let points = client
+        .get_ema(GetMovingAverageRequest {
+            max_points: 4,
+            ..moving_average_request(2, 9)
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    assert_eq!(points.len(), 4);
+    assert_eq!(points[0].timestamp, micros(1));
+    assert_eq!(points[3].timestamp, micros(9));
+}
+
+#[tokio::test]
+async fn stream_indicator_repeats_the_outputs_in_every_chunk() {
+    let mut client = client().await;
+    let chunks: Vec<ComputeIndicatorResponse> = client
+        .stream_indicator(ComputeIndicatorRequest {
+            page_size: 5,
+            ..indicator_request("sma", &[("window", 3.0)])
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(chunks.len(), 2);
+    for chunk in &chunks {
+        assert_eq!(chunk.outputs, vec!["sma"]);
+        assert_eq!(chunk.warm_up, 2);
+    }
+    assert_eq!(chunks[1].points.len(), 3);
+}
+
+#[tokio::test]
+async fn candles_bucket_trades_by_interval() {
+    let mut client = client().await;
+    let candles = client
+        .get_candles(GetCandlesRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(5),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .candles;
+
+    assert_eq!(candles.len(), 2);
+    let (first, second) = (&candles[0], &candles[1]);
+    assert_eq!(first.timestamp, micros(0));
+    assert_eq!(
+        (first.open, first.high, first.low, first.close),
+        (100.0, 104.0, 100.0, 104.0)
+    );
+    assert_close(first.volume, 7.0);
+    assert_close(first.vwap, 102.0);
+    assert_eq!(first.trades_count, 5);
+    assert_close(first.buy_volume, 3.0);
+    assert_close(first.sell_volume, 4.0);
+
+    assert_eq!(second.timestamp, micros(5));
+    assert_eq!(
+        (second.open, second.high, second.low, second.close),
+        (105.0, 109.0, 105.0, 109.0)
+    );
+    assert_close(second.volume, 8.0);
+    assert_close(second.vwap, 107.0);
+    assert_close(second.buy_volume, 2.0);
+    assert_close(second.sell_volume, 6.0);
+}
+
+#[tokio::test]
+async fn candles_require_a_positive_interval() {
+    let mut client = client().await;
+    let status = client
+        .get_candles(GetCandlesRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(0),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+// With 2s candles the fixture closes at 101, 103, ..., 109, and every
+// candle spans 1 with a gap of 1 from the previous close, so true range is 2.
+
+#[tokio::test]
+async fn bollinger_bands_over_candle_closes() {
+    let mut client = client().await;
+    let points = client
+        .get_bollinger_bands(GetBollingerBandsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(2),
+            window_size: 3,
+            num_std_dev: 0.0,
+            series_type: SeriesType::Close as i32,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // Each window is m - 2, m, m + 2: population stddev sqrt(8 / 3), two of them.
+    let width = 2.0 * (8.0_f64 / 3.0).sqrt();
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].timestamp, micros(4));
+    for (point, middle) in points.iter().zip([103.0, 105.0, 107.0]) {
+        assert_close(point.middle, middle);
+        assert_close(point.upper, middle + width);
+        assert_close(point.lower, middle - width);
+    }
+}
+
+#[tokio::test]
+async fn atr_over_candles() {
+    let mut client = client().await;
+    let points = client
+        .get_atr(GetAtrRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(2),
+            window_size: 2,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].timestamp, micros(4));
+    for point in &points {
+        assert_close(point.value, 2.0);
+    }
+}
+
+#[tokio::test]
+async fn atr_requires_an_interval() {
+    let mut client = client().await;
+    let status = client
+        .get_atr(GetAtrRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: None,
+            window_size: 2,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn keltner_channels_over_candles() {
+    let mut client = client().await;
+    let points = client
+        .get_keltner_channels(GetKeltnerChannelsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(2),
+            window_size: 2,
+            atr_window_size: 2,
+            multiplier: 0.0,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+
+    // The 2-period EMA trails closes rising by 2 per candle by 1, and the
+    // bands sit two ATRs of 2 away from it.
+    assert_eq!(points.len(), 3);
+    assert_eq!(points[0].timestamp, micros(4));
+    for (point, middle) in points.iter().zip([104.0, 106.0, 108.0]) {
+        assert_close(point.middle, middle);
+        assert_close(point.upper, middle + 4.0);
+        assert_close(point.lower, middle - 4.0);
+    }
+}
+
+#[tokio::test]
+async fn keltner_channels_without_enough_candles_are_rejected() {
+    let mut client = client().await;
+    let status = client
+        .get_keltner_channels(GetKeltnerChannelsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(2),
+            window_size: 20,
+            atr_window_size: 10,
+            multiplier: 2.0,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "Not enough data to compute Keltner Channels"
+    );
+}
+
+#[tokio::test]
+async fn stream_bollinger_bands_resumes_like_the_unary_call() {
+    let mut client = client().await;
+    let request = GetBollingerBandsRequest {
+        symbol: SYMBOL.to_string(),
+        start_timestamp: timestamp(0),
+        end_timestamp: timestamp(9),
+        interval: seconds(1),
+        window_size: 3,
+        num_std_dev: 2.0,
+        series_type: SeriesType::Close as i32,
+        page_size: 0,
+        page_token: String::new(),
+        max_points: 0,
+    };
+    let all = client
+        .get_bollinger_bands(request.clone())
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(all.next_page_token.is_empty());
+    let chunks: Vec<GetBandsResponse> = client
+        .stream_bollinger_bands(GetBollingerBandsRequest {
+            page_size: 3,
+            ..request.clone()
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    let sizes: Vec<usize> = chunks.iter().map(|c| c.points.len()).collect();
+    assert_eq!(sizes, vec![3, 3, 2]);
+    let resumed = client
+        .get_bollinger_bands(GetBollingerBandsRequest {
+            page_size: 3,
+            page_token: chunks[0].next_page_token.clone(),
+            ..request
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(resumed, chunks[1]);
+
+    let streamed: Vec<_> = chunks.into_iter().flat_map(|c| c.points).collect();
+    assert_eq!(streamed, all.points);
+}
+
+fn indicator_request(name: &str, params: &[(&str, f64)]) -> ComputeIndicatorRequest {
+    ComputeIndicatorRequest {
+        name: name.to_string(),
+        params: params
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect(),
+        symbol: SYMBOL.to_string(),
+        start_timestamp: timestamp(0),
+        end_timestamp: timestamp(9),
+        interval: None,
+        series_type: SeriesType::Close as i32,
+        page_size: 0,
+        page_token: String::new(),
+        max_points: 0,
+    }
+}
+
+#[tokio::test]
+async fn compute_indicator_sma_over_trades() {
+    let mut client = client().await;
+    let response = client
+        .compute_indicator(indicator_request("SMA", &[("window", 3.0)]))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.outputs, vec!["sma"]);
+    assert_eq!(response.warm_up, 2);
+    assert_eq!(response.points.len(), 8);
+    assert_eq!(response.points[0].timestamp, micros(2));
+    for (point, expected) in response.points.iter().zip(101..) {
+        assert_close(point.values[0], expected as f64);
+    }
+}
+
+#[tokio::test]
+async fn compute_indicator_matches_the_dedicated_rpc() {
+    let mut client = client().await;
+    let bands = client
+        .get_keltner_channels(GetKeltnerChannelsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+            interval: seconds(2),
+            window_size: 2,
+            atr_window_size: 2,
+            multiplier: 0.0,
+            page_size: 0,
+            page_token: String::new(),
+            max_points: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .points;
+    let response = client
+        .compute_indicator(ComputeIndicatorRequest {
+            interval: seconds(2),
+            ..indicator_request("keltner", &[("window", 2.0), ("atr_window", 2.0)])
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.outputs, vec!["upper", "middle", "lower"]);
+    assert_eq!(response.points.len(), bands.len());
+    for (point, band) in response.points.iter().zip(&bands) {
+        assert_eq!(point.timestamp, band.timestamp);
+        assert_eq!(point.values, vec![band.upper, band.middle, band.lower]);
+    }
+}
+
+#[tokio::test]
+async fn compute_indicator_rejects_bad_requests() {
+    let mut client = client().await;
+    let cases = [
+        (indicator_request("vortex", &[]), Code::NotFound),
+        (
+            indicator_request("sma", &[("period", 3.0)]),
+            Code::InvalidArgument,
+        ),
+        (
+            indicator_request("sma", &[("window", 2.5)]),
+            Code::InvalidArgument,
+        ),
+        (
+            indicator_request("macd", &[("fast_period", 5.0), ("slow_period", 3.0)]),
+            Code::InvalidArgument,
+        ),
+        // ATR needs whole candles.
+        (indicator_request("atr", &[]), Code::InvalidArgument),
+        (
+            indicator_request("ema", &[("window", 20.0)]),
+            Code::InvalidArgument,
+        ),
+    ];
+    for (request, code) in cases {
+        let name = request.name.clone();
+        let status = client.compute_indicator(request).await.unwrap_err();
+        assert_eq!(status.code(), code, "{name}: {}", status.message());
+    }
+}
+
+#[tokio::test]
+async fn list_indicators_describes_the_registry() {
+    let mut client = client().await;
+    let indicators = client
+        .list_indicators(ListIndicatorsRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .indicators;
+
+    let names: Vec<&str> = indicators.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["sma", "ema", "rsi", "macd", "bollinger", "atr", "keltner"]
+    );
+    let macd = &indicators[3];
+    assert_eq!(macd.outputs, vec!["macd", "signal", "histogram"]);
+    let defaults: Vec<(&str, f64)> = macd
+        .parameters
+        .iter()
+        .map(|p| (p.name.as_str(), p.default_value))
+        .collect();
+    assert_eq!(
+        defaults,
+        [
+            ("fast_period", 12.0),
+            ("slow_period", 26.0),
+            ("signal_period", 9.0)
+        ]
+    );
+    assert_eq!(indicators[5].input(), IndicatorInput::Candles);
+}
+
+#[tokio::test]
+async fn subscribe_streams_only_the_requested_symbol() {
+    let mut client = client().await;
+    let trades: Vec<data::Trade> = client
+        .subscribe_to_trades(SubscribeToTradesRequest {
+            symbol: "ethusdt".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|trade| trade.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].trade_id, "eth-0");
+}
+
+/// Serves the fixture as history, with a feed that replays the last stored
+/// trade and then trades at 2100-01-01 + 0, 1 and 2 seconds priced 200, 202
+/// and 204.
+async fn live_client() -> AnalyticsServiceClient<Channel> {
+    let history = fixture();
+    let mut live = vec![history[9].clone()];
+    for i in 0..3 {
+        live.push(data::Trade {
+            price: 200.0 + 2.0 * i as f64,
+            exchange_timestamp: ((LIVE_SECONDS + i) * 1_000_000) as u64,
+            trade_id: format!("live-{i}"),
+            ..history[0].clone()
+        });
+    }
+    serve(store(&history), MemoryFeed::new(live)).await
+}
+
+fn subscribe_request(provisional: bool) -> SubscribeToIndicatorRequest {
+    SubscribeToIndicatorRequest {
+        symbol: SYMBOL.to_string(),
+        name: "sma".to_string(),
+        params: [("window".to_string(), 2.0)].into(),
+        interval: seconds(2),
+        series_type: SeriesType::Close as i32,
+        start_timestamp: timestamp(0),
+        provisional,
+    }
+}
+
+#[tokio::test]
+async fn subscribe_to_indicator_emits_on_candle_close() {
+    let mut client = live_client().await;
+    let updates: Vec<IndicatorUpdate> = client
+        .subscribe_to_indicator(subscribe_request(false))
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|update| update.unwrap())
+        .collect()
+        .await;
+
+    // The store's last trade at 9s leaves the candle at 8s open, so the
+    // seeded value is over the candles closing at 105 and 107. The first
+    // live trade closes the one at 8s, and the trade at +2 closes the first
+    // live candle at 202. The replayed stored trade is skipped.
+    let expected = [
+        (micros(6), 106.0),
+        (micros(8), 108.0),
+        ((LIVE_SECONDS * 1_000_000) as u64, 155.5),
+    ];
+    assert_eq!(updates.len(), expected.len());
+    for (update, (timestamp, value)) in updates.iter().zip(expected) {
+        assert_eq!(update.timestamp, timestamp);
+        assert_close(update.values[0], value);
+        assert!(!update.provisional);
+    }
+}
+
+#[tokio::test]
+async fn subscribe_to_indicator_with_provisional_values() {
+    let mut client = live_client().await;
+    let updates: Vec<(u64, f64, bool)> = client
+        .subscribe_to_indicator(subscribe_request(true))
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|update| {
+            let update = update.unwrap();
+            (update.timestamp, update.values[0], update.provisional)
+        })
+        .collect()
+        .await;
+
+    let live = |offset: i64| ((LIVE_SECONDS + offset) * 1_000_000) as u64;
+    assert_eq!(
+        updates,
+        vec![
+            (micros(6), 106.0, false),
+            (micros(8), 108.0, false),
+            (live(0), 154.5, true),
+            (live(0), 155.5, true),
+            (live(0), 155.5, false),
+            (live(2), 203.0, true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn subscribe_to_indicator_takes_trades_past_the_watermark_from_the_feed() {
+    // The store has ingested up to the trade at 7s; the feed carries the
+    // two it has not, as if the sink had yet to flush them.
+    let history = fixture();
+    let mut live = history[8..10].to_vec();
+    live.push(data::Trade {
+        price: 200.0,
+        exchange_timestamp: (LIVE_SECONDS * 1_000_000) as u64,
+        trade_id: "live-0".to_string(),
+        ..history[0].clone()
+    });
+    let mut client = serve(store(&history[..8]), MemoryFeed::new(live)).await;
+    let updates: Vec<(u64, f64)> = client
+        .subscribe_to_indicator(subscribe_request(false))
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|update| {
+            let update = update.unwrap();
+            (update.timestamp, update.values[0])
+        })
+        .collect()
+        .await;
+
+    // The candle at 6s stays open across the seeding, and closes at 107
+    // once the trade at 8s arrives.
+    assert_eq!(
+        updates,
+        vec![(micros(4), 104.0), (micros(6), 106.0), (micros(8), 108.0)]
+    );
+}
+
+#[tokio::test]
+async fn subscribe_to_indicator_requires_an_interval() {
+    let mut client = live_client().await;
+    let status = client
+        .subscribe_to_indicator(SubscribeToIndicatorRequest {
+            interval: None,
+            ..subscribe_request(false)
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn arrow_file_store_serves_the_fixture() {
+    let trades = fixture();
+    let batch = RecordBatch::try_from_iter(vec![
+        (
+            "symbol",
+            Arc::new(StringArray::from_iter_values(
+                trades.iter().map(|t| t.symbol.as_str()),
+            )) as _,
+        ),
+        (
+            "timestamp",
+            Arc::new(TimestampNanosecondArray::from_iter_values(
+                trades.iter().map(|t| t.exchange_timestamp as i64 * 1000),
+            )) as _,
+        ),
+        (
+            "price",
+            Arc::new(Float64Array::from_iter_values(
+                trades.iter().map(|t| t.price),
+            )) as _,
+        ),
+        (
+            "quantity",
+            Arc::new(Float64Array::from_iter_values(
+                trades.iter().map(|t| t.quantity),
+            )) as _,
+        ),
+    ])
+    .unwrap();
+    let path = std::env::temp_dir().join(format!("analytics-fixture-{}.arrow", std::process::id()));
+    let mut writer =
+        FileWriter::try_new(std::fs::File::create(&path).unwrap(), &batch.schema()).unwrap();
+    writer.write(&batch).unwrap();
+    writer.finish().unwrap();
+
+    let store = MemoryStore::from_arrow_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mut client = serve(store, MemoryFeed::default()).await;
+    let response = client
+        .get_trade_analytics(GetTradeAnalyticsRequest {
+            symbol: SYMBOL.to_string(),
+            start_timestamp: timestamp(0),
+            end_timestamp: timestamp(9),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.trades_count, 10);
+    // Even trades (quantity 1) are 100, 102, ..., 108; odd (quantity 2) 101, ..., 109.
+    assert_close(response.total_volume_in_quotes, 520.0 + 2.0 * 525.0);
+}
+
+fn query_request(sql: &str, max_rows: u64) -> ExecuteQueryRequest {
+    ExecuteQueryRequest {
+        sql: sql.to_string(),
+        max_rows,
+    }
+}
+
+fn read_ipc(bytes: &[u8]) -> RecordBatch {
+    let reader = StreamReader::try_new(std::io::Cursor::new(bytes), None).unwrap();
+    let schema = reader.schema();
+    let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+    concat_batches(&schema, &batches).unwrap()
+}
+
+#[tokio::test]
+async fn execute_query_returns_arrow_ipc() {
+    let mut client = client().await;
+    let response = client
+        .execute_query(query_request(
+            "SELECT symbol, COUNT(*) AS trades, SUM(quantity) AS volume
+             FROM trades GROUP BY symbol ORDER BY symbol",
+            0,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.rows, 2);
+    assert!(!response.truncated);
+    let batch = read_ipc(&response.arrow_ipc);
+    let symbols = batch.column(0).as_string::<i32>();
+    let trades = batch.column(1).as_primitive::<Int64Type>();
+    let volumes = batch.column(2).as_primitive::<Float64Type>();
+    assert_eq!(symbols.value(0), SYMBOL);
+    assert_eq!(trades.value(0), 10);
+    assert_close(volumes.value(0), 15.0);
+    assert_eq!(symbols.value(1), "ETHUSDT");
+    assert_eq!(trades.value(1), 1);
+}
+
+#[tokio::test]
+async fn execute_query_reads_minute_candles() {
+    let mut client = client().await;
+    let response = client
+        .execute_query(query_request(
+            "SELECT timestamp, open, close, trade_count FROM candles
+             WHERE symbol = 'BTCUSDT' ORDER BY timestamp",
+            0,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+
+    // START_SECONDS is 20 seconds into a minute, so all ten trades share one.
+    let batch = read_ipc(&response.arrow_ipc);
+    assert_eq!(batch.num_rows(), 1);
+    assert_eq!(
+        batch.column(0).as_primitive::<Int64Type>().value(0),
+        micros(-20) as i64
+    );
+    assert_eq!(
+        batch.column(1).as_primitive::<Float64Type>().value(0),
+        100.0
+    );
+    assert_eq!(
+        batch.column(2).as_primitive::<Float64Type>().value(0),
+        109.0
+    );
+    assert_eq!(batch.column(3).as_primitive::<UInt64Type>().value(0), 10);
+}
+
+#[tokio::test]
+async fn execute_query_truncates_at_max_rows() {
+    let mut client = client().await;
+    let response = client
+        .execute_query(query_request(
+            "SELECT price FROM trades WHERE symbol = 'BTCUSDT' ORDER BY price DESC",
+            3,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.rows, 3);
+    assert!(response.truncated);
+    let batch = read_ipc(&response.arrow_ipc);
+    let prices = batch.column(0).as_primitive::<Float64Type>();
+    assert_eq!(prices.values().to_vec(), vec![109.0, 108.0, 107.0]);
+}
+
+#[tokio::test]
+async fn execute_query_is_sandboxed() {
+    let mut client = client().await;
+    for (sql, code) in [
+        ("SELECT random() FROM trades", Code::PermissionDenied),
+        (
+            "SELECT * FROM generate_series(1, 10)",
+            Code::PermissionDenied,
+        ),
+        (
+            "CREATE TABLE snapshot AS SELECT * FROM trades",
+            Code::InvalidArgument,
+        ),
+        ("DROP TABLE trades", Code::InvalidArgument),
+        ("SELECT * FROM missing", Code::InvalidArgument),
+    ] {
+        let status = client
+            .execute_query(query_request(sql, 0))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), code, "{sql}: {}", status.message());
+    }
+}
+
+async fn flight_client() -> FlightServiceClient<Channel> {
+    let trades = fixture();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler =
+        AnalyticsServiceHandler::new(Arc::new(store(&trades)), Arc::new(MemoryFeed::new(trades)));
+    tokio::spawn(
+        Server::builder()
+            .add_service(FlightServiceServer::new(AnalyticsFlightService::new(
+                Arc::new(handler),
+            )))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    FlightServiceClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap()
+}
+
+/// Fetches `command` with `DoGet` and reads the messages back as an IPC
+/// stream.
+async fn do_get(client: &mut FlightServiceClient<Channel>, command: &str) -> RecordBatch {
+    let messages: Vec<FlightData> = client
+        .do_get(Ticket {
+            ticket: command.as_bytes().to_vec(),
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    let mut ipc = Vec::new();
+    for message in messages {
+        let padding = (8 - message.data_header.len() % 8) % 8;
+        ipc.extend(0xFFFF_FFFFu32.to_le_bytes());
+        ipc.extend(((message.data_header.len() + padding) as i32).to_le_bytes());
+        ipc.extend(&message.data_header);
+        ipc.extend(vec![0; padding]);
+        ipc.extend(&message.data_body);
+    }
+    read_ipc(&ipc)
+}
+
+#[tokio::test]
+async fn flight_serves_trades() {
+    let mut client = flight_client().await;
+    let command = format!(
+        r#"{{"trades": {{"symbol": "{SYMBOL}", "start": {}, "end": {}}}}}"#,
+        micros(2),
+        micros(4)
+    );
+    let batch = do_get(&mut client, &command).await;
+    let mut prices = batch
+        .column_by_name("price")
+        .unwrap()
+        .as_primitive::<Float64Type>()
+        .values()
+        .to_vec();
+    prices.sort_by(f64::total_cmp);
+    assert_eq!(prices, vec![102.0, 103.0, 104.0]);
+
+    let batch = do_get(&mut client, r#"{"trades": {}}"#).await;
+    assert_eq!(batch.num_rows(), 11);
+}
+
+#[tokio::test]
+async fn flight_serves_candles() {
+    let mut client = flight_client().await;
+    let batch = do_get(&mut client, r#"{"candles": {"symbol": "BTCUSDT"}}"#).await;
+    assert_eq!(batch.num_rows(), 1);
+    let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+    assert_eq!(
+        column("timestamp").as_primitive::<Int64Type>().value(0),
+        micros(-20) as i64
+    );
+    assert_eq!(column("open").as_primitive::<Float64Type>().value(0), 100.0);
+    assert_eq!(
+        column("close").as_primitive::<Float64Type>().value(0),
+        109.0
+    );
+    assert_eq!(
+        column("trade_count").as_primitive::<UInt64Type>().value(0),
+        10
+    );
+}
+
+#[tokio::test]
+async fn flight_rejects_invalid_candle_intervals() {
+    let mut client = flight_client().await;
+    for interval_secs in [0, u64::MAX] {
+        let command =
+            format!(r#"{{"candles": {{"symbol": "BTCUSDT", "interval_secs": {interval_secs}}}}}"#);
+        let status = client
+            .do_get(Ticket {
+                ticket: command.into_bytes(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument, "{interval_secs}");
+    }
+}
+
+#[tokio::test]
+async fn flight_serves_indicator_outputs() {
+    let mut client = flight_client().await;
+    let command = format!(
+        r#"{{"indicator": {{"name": "sma", "symbol": "{SYMBOL}", "params": {{"window": 3}}, "end": {}}}}}"#,
+        micros(4)
+    );
+    let batch = do_get(&mut client, &command).await;
+    let timestamps = batch
+        .column_by_name("timestamp")
+        .unwrap()
+        .as_primitive::<Int64Type>();
+    let sma = batch
+        .column_by_name("sma")
+        .unwrap()
+        .as_primitive::<Float64Type>();
+    assert_eq!(
+        timestamps.values().to_vec(),
+        (2..5).map(|i| micros(i) as i64).collect::<Vec<_>>()
+    );
+    assert_eq!(sma.values().to_vec(), vec![101.0, 102.0, 103.0]);
+}
+
+#[tokio::test]
+async fn flight_serves_sandboxed_queries() {
+    let mut client = flight_client().await;
+    let batch = do_get(
+        &mut client,
+        r#"{"query": "SELECT symbol, COUNT(*) AS trades FROM trades GROUP BY symbol ORDER BY symbol"}"#,
+    )
+    .await;
+    assert_eq!(batch.column(0).as_string::<i32>().value(0), "BTCUSDT");
+    assert_eq!(batch.column(1).as_primitive::<Int64Type>().value(0), 10);
+
+    let status = client
+        .do_get(Ticket {
+            ticket: br#"{"query": "SELECT random()"}"#.to_vec(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn flight_info_describes_commands() {
+    let mut client = flight_client().await;
+    let command = br#"{"candles": {"symbol": "BTCUSDT", "interval_secs": 300}}"#.to_vec();
+    let info = client
+        .get_flight_info(FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd: command.clone(),
+            path: Vec::new(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let schema = StreamReader::try_new(std::io::Cursor::new(info.schema), None)
+        .unwrap()
+        .schema();
+    assert_eq!(schema.field(1).name(), "timestamp");
+    assert_eq!(info.endpoint.len(), 1);
+    assert_eq!(info.endpoint[0].ticket.as_ref().unwrap().ticket, command);
+
+    let status = client
+        .get_flight_info(FlightDescriptor {
+            r#type: DescriptorType::Cmd as i32,
+            cmd: b"{\"candles\": 1}".to_vec(),
+            path: Vec::new(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let paths: Vec<Vec<String>> = client
+        .list_flights(Criteria::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .map(|info| info.unwrap().flight_descriptor.unwrap().path)
+        .collect()
+        .await;
+    assert!(paths.contains(&vec!["trades".to_string()]));
+    assert!(paths.contains(&vec!["candles".to_string()]));
+    assert!(paths.contains(&vec!["indicator".to_string(), "macd".to_string()]));
+}
+
+#[tokio::test]
+async fn closed_windows_are_served_from_the_cache() {
+    let trades = fixture();
+    let handler =
+        AnalyticsServiceHandler::new(Arc::new(store(&trades)), Arc::new(MemoryFeed::new(trades)))
+            .with_cache(CacheConfig {
+                watermark_lag: Duration::ZERO,
+                ..CacheConfig::default()
+            });
+    let metrics = handler.metrics();
+    let mut client = serve_handler(handler).await;
+    let requests = |rpc: &str, result: &str| {
+        metrics
+            .cache_requests
+            .with_label_values(&[rpc, result])
+            .get()
+    };
+
+    // The last BTCUSDT trade is at 9s, so a window ending at 4s is closed.
+    let analytics = GetTradeAnalyticsRequest {
+        symbol: SYMBOL.to_string(),
+        start_timestamp: timestamp(0),
+        end_timestamp: timestamp(4),
+    };
+    let first = client
+        .get_trade_analytics(analytics.clone())
+        .await
+        .unwrap()
+        .into_inner();
+    let second = client
+        .get_trade_analytics(analytics)
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(first, second);
+    assert_eq!(requests("GetTradeAnalytics", "miss"), 1);
+    assert_eq!(requests("GetTradeAnalytics", "hit"), 1);
+
+    // A window reaching the watermark may still receive trades.
+    let macd = |end_offset, page_size| GetMacdRequest {
+        symbol: SYMBOL.to_string(),
+        start_timestamp: timestamp(0),
+        end_timestamp: timestamp(end_offset),
+        fast_period: 2,
+        slow_period: 3,
+        signal_period: 2,
+        interval: None,
+        series_type: SeriesType::Close as i32,
+        page_size,
+        page_token: String::new(),
+        max_points: 0,
+    };
+    client.get_macd(macd(9, 0)).await.unwrap();
+    assert_eq!(requests("GetMacd", "bypass"), 1);
+
+    // Pages of a closed window share one cached series.
+    let all = client.get_macd(macd(8, 0)).await.unwrap().into_inner();
+    let page = client.get_macd(macd(8, 2)).await.unwrap().into_inner();
+    assert_eq!(page.points, all.points[..2]);
+    assert_eq!(requests("GetMacd", "miss"), 1);
+    assert_eq!(requests("GetMacd", "hit"), 1);
+}
@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use analytics_server::analytics::{IndicatorUpdate, SeriesType};
+use analytics_server::data::{self, trade::Side};
+use analytics_server::indicators::streaming::{
+    AtrState, BollingerState, EmaState, Hlc, KeltnerState, MacdState, RsiState, SmaState, Streaming,
+};
+use analytics_server::indicators::{self, Band, Macd, MacdPeriods};
+use analytics_server::live::LiveIndicator;
+use analytics_server::registry::{Indicator, Inputs, Params, Registry};
+use analytics_server::store::{self, Candle, Trades};
+use proptest::prelude::*;
+
+fn values() -> impl Strategy<Value = Vec<f64>> {
+    prop::collection::vec(-1e6f64..1e6, 0..200)
+}
+
+fn candles() -> impl Strategy<Value = Vec<Candle>> {
+    prop::collection::vec(
+        (1.0f64..1e4, 0.0f64..100.0, 0.0f64..100.0, 0.0f64..=1.0),
+        0..200,
+    )
+    .prop_map(|bars| {
+        bars.into_iter()
+            .enumerate()
+            .map(|(i, (open, up, down, at))| {
+                let (high, low) = (open + up, open - down);
+                Candle {
+                    timestamp: i as u64,
+                    open,
+                    high,
+                    low,
+                    close: low + (high - low) * at,
+                    volume: 1.0,
+                    quote_volume: open,
+                    trade_count: 1,
+                    buy_volume: 1.0,
+                    sell_volume: 0.0,
+                }
+            })
+            .collect()
+    })
+}
+
+/// Feeds `inputs` one at a time, moving to a state restored from a snapshot
+/// after the first `split`.
+fn stream_with_restore<S: Streaming>(
+    mut state: S,
+    inputs: &[S::Input],
+    split: usize,
+) -> Vec<S::Output> {
+    let split = split.min(inputs.len());
+    let mut outputs: Vec<S::Output> = inputs[..split]
+        .iter()
+        .filter_map(|input| state.update(*input))
+        .collect();
+    let mut resumed = S::restore(state.snapshot());
+    outputs.extend(
+        inputs[split..]
+            .iter()
+            .filter_map(|input| resumed.update(*input)),
+    );
+    outputs
+}
+
+/// `indicator`'s params with its integer ones set from `periods`, if valid.
+fn params(indicator: &dyn Indicator, periods: &[u32]) -> Option<Params> {
+    let values: HashMap<String, f64> = indicator
+        .params()
+        .iter()
+        .zip(periods)
+        .filter(|(spec, _)| spec.integer)
+        .map(|(spec, period)| (spec.name.to_string(), *period as f64))
+        .collect();
+    let params = Params::resolve(indicator.params(), &values).ok()?;
+    indicator.validate(&params).ok()?;
+    Some(params)
+}
+
+/// What `ComputeIndicator` returns over `candles`, by candle index.
+fn compute(indicator: &dyn Indicator, params: &Params, candles: &[Candle]) -> Vec<Vec<f64>> {
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    if closes.len() <= indicator.warm_up(params) {
+        return Vec::new();
+    }
+    let inputs = Inputs {
+        values: &closes,
+        candles,
+    };
+    indicator.compute(&inputs, params)
+}
+
+/// Wilder smoothing seeded with the mean of the first `window` values.
+fn wilder(values: &[f64], window: usize) -> Vec<f64> {
+    if values.len() < window {
+        return Vec::new();
+    }
+    let period = window as f64;
+    let mut average = values[..window].iter().sum::<f64>() / period;
+    let mut smoothed = vec![average];
+    for value in &values[window..] {
+        average = (average * (period - 1.0) + value) / period;
+        smoothed.push(average);
+    }
+    smoothed
+}
+
+proptest! {
+    #[test]
+    fn sma_streaming_matches_batch(values in values(), window in 1usize..30, split in 0usize..200) {
+        let batch = indicators::sma(&values, window);
+        prop_assert_eq!(stream_with_restore(SmaState::new(window), &values, split), batch.clone());
+
+        prop_assert_eq!(batch.len(), (values.len() + 1).saturating_sub(window));
+        for (sma, window) in batch.iter().zip(values.windows(window)) {
+            let mean = window.iter().sum::<f64>() / window.len() as f64;
+            prop_assert!((sma - mean).abs() < 1e-6, "{} != {}", sma, mean);
+        }
+    }
+
+    #[test]
+    fn ema_streaming_matches_batch(values in values(), window in 1usize..30, split in 0usize..200) {
+        let batch = indicators::ema(&values, window);
+        prop_assert_eq!(stream_with_restore(EmaState::new(window), &values, split), batch.clone());
+
+        prop_assert_eq!(batch.len(), (values.len() + 1).saturating_sub(window));
+        if let Some(first) = batch.first() {
+            let seed = values[..window].iter().sum::<f64>() / window as f64;
+            prop_assert!((first - seed).abs() < 1e-6, "{} != {}", first, seed);
+        }
+        let multiplier = 2.0 / (window as f64 + 1.0);
+        for (pair, value) in batch.windows(2).zip(&values[window.min(values.len())..]) {
+            prop_assert_eq!(pair[1], value * multiplier + pair[0] * (1.0 - multiplier));
+        }
+    }
+
+    #[test]
+    fn macd_streaming_matches_batch(
+        values in values(),
+        fast in 1usize..15,
+        extra in 1usize..15,
+        signal in 1usize..10,
+        split in 0usize..200,
+    ) {
+        let slow = fast + extra;
+        let periods = MacdPeriods::new(fast, slow, signal).unwrap();
+        let batch = indicators::macd(&values, periods);
+        prop_assert_eq!(
+            stream_with_restore(MacdState::new(periods), &values, split),
+            batch.clone()
+        );
+
+        // Both EMAs are seeded over windows ending at values[slow - 1].
+        let fast_ema = indicators::ema(&values[(slow - fast).min(values.len())..], fast);
+        let slow_ema = indicators::ema(&values, slow);
+        let line: Vec<f64> = fast_ema
+            .iter()
+            .zip(&slow_ema)
+            .map(|(fast, slow)| fast - slow)
+            .collect();
+        let expected: Vec<Macd> = line
+            .iter()
+            .skip(signal - 1)
+            .zip(indicators::ema(&line, signal))
+            .map(|(macd, signal)| Macd { macd: *macd, signal, histogram: macd - signal })
+            .collect();
+        prop_assert_eq!(batch.len(), values.len().saturating_sub(periods.warm_up()));
+        prop_assert_eq!(batch, expected);
+    }
+
+    #[test]
+    fn rsi_streaming_matches_batch(values in values(), window in 1usize..30, split in 0usize..200) {
+        let batch = indicators::rsi(&values, window);
+        prop_assert_eq!(stream_with_restore(RsiState::new(window), &values, split), batch.clone());
+
+        let changes: Vec<f64> = values.windows(2).map(|w| w[1] - w[0]).collect();
+        let gains: Vec<f64> = changes.iter().map(|c| c.max(0.0)).collect();
+        let losses: Vec<f64> = changes.iter().map(|c| (-c).max(0.0)).collect();
+        let expected: Vec<f64> = wilder(&gains, window)
+            .into_iter()
+            .zip(wilder(&losses, window))
+            .map(|(gain, loss)| match (gain == 0.0, loss == 0.0) {
+                (true, true) => 50.0,
+                (false, true) => 100.0,
+                _ => 100.0 - 100.0 / (1.0 + gain / loss),
+            })
+            .collect();
+        prop_assert_eq!(batch.len(), values.len().saturating_sub(window));
+        for (rsi, expected) in batch.iter().zip(&expected) {
+            prop_assert!((rsi - expected).abs() < 1e-9, "{} != {}", rsi, expected);
+        }
+    }
+
+    #[test]
+    fn bollinger_streaming_matches_batch(
+        values in values(),
+        window in 1usize..30,
+        num_std_dev in 0.0f64..4.0,
+        split in 0usize..200,
+    ) {
+        let batch = indicators::bollinger_bands(&values, window, num_std_dev);
+        prop_assert_eq!(
+            stream_with_restore(BollingerState::new(window, num_std_dev), &values, split),
+            batch.clone()
+        );
+
+        prop_assert_eq!(batch.len(), (values.len() + 1).saturating_sub(window));
+        for (band, window) in batch.iter().zip(values.windows(window)) {
+            let mean = window.iter().sum::<f64>() / window.len() as f64;
+            let variance =
+                window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / window.len() as f64;
+            let width = num_std_dev * variance.sqrt();
+            // The state keeps running sums, so it only matches to rounding,
+            // which the square root magnifies for a nearly flat window.
+            let tolerance = 1e-6 * window.iter().fold(1.0, |max: f64, v| max.max(v.abs()));
+            for (actual, expected) in [
+                (band.upper, mean + width),
+                (band.middle, mean),
+                (band.lower, mean - width),
+            ] {
+                prop_assert!((actual - expected).abs() < tolerance, "{} != {}", actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn atr_streaming_matches_batch(candles in candles(), window in 1usize..30, split in 0usize..200) {
+        let batch = indicators::atr(&candles, window);
+        let hlc: Vec<Hlc> = candles.iter().map(Hlc::from).collect();
+        prop_assert_eq!(stream_with_restore(AtrState::new(window), &hlc, split), batch.clone());
+
+        let expected = wilder(&indicators::true_range(&candles), window);
+        prop_assert_eq!(batch.len(), candles.len().saturating_sub(window));
+        for (atr, expected) in batch.iter().zip(&expected) {
+            prop_assert!((atr - expected).abs() < 1e-9, "{} != {}", atr, expected);
+        }
+    }
+
+    #[test]
+    fn keltner_streaming_matches_batch(
+        candles in candles(),
+        ema_window in 1usize..30,
+        atr_window in 1usize..30,
+        split in 0usize..200,
+    ) {
+        let batch = indicators::keltner_channels(&candles, ema_window, atr_window, 2.0);
+        let hlc: Vec<Hlc> = candles.iter().map(Hlc::from).collect();
+        prop_assert_eq!(
+            stream_with_restore(KeltnerState::new(ema_window, atr_window, 2.0), &hlc, split),
+            batch.clone()
+        );
+
+        // Both lines start at the candle where the later of the two warms up.
+        let start = indicators::keltner_warm_up(ema_window, atr_window);
+        let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+        let ema = indicators::ema(&closes, ema_window);
+        let atr = indicators::atr(&candles, atr_window);
+        let expected: Vec<Band> = ema
+            .iter()
+            .skip(start + 1 - ema_window)
+            .zip(atr.iter().skip(start - atr_window))
+            .map(|(middle, atr)| Band {
+                upper: middle + 2.0 * atr,
+                middle: *middle,
+                lower: middle - 2.0 * atr,
+            })
+            .collect();
+        prop_assert_eq!(batch.len(), candles.len().saturating_sub(start));
+        prop_assert_eq!(batch, expected);
+    }
+
+    /// Every builtin indicator's `streaming` hook, forked part way through,
+    /// yields the rows `compute` returns over the same candles.
+    #[test]
+    fn registered_states_match_compute(
+        candles in candles(),
+        periods in prop::collection::vec(1u32..15, 3),
+        split in 0usize..200,
+    ) {
+        let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+        let split = split.min(candles.len());
+        for indicator in Registry::builtin().iter() {
+            let Some(params) = params(indicator, &periods) else {
+                continue;
+            };
+            let expected = compute(indicator, &params, &candles);
+
+            let mut state = indicator.streaming(&params).unwrap();
+            let mut rows: Vec<Vec<f64>> = candles[..split]
+                .iter()
+                .zip(&closes)
+                .filter_map(|(candle, close)| state.update(*close, Some(candle)))
+                .collect();
+            let mut forked = state.fork();
+            rows.extend(
+                candles[split..]
+                    .iter()
+                    .zip(&closes[split..])
+                    .filter_map(|(candle, close)| forked.update(*close, Some(candle))),
+            );
+            prop_assert_eq!(rows, expected, "{}", indicator.name());
+        }
+    }
+
+    /// A subscription seeded with the first `split` trades and fed the rest
+    /// live sends, on each closed candle, the row `ComputeIndicator` has for
+    /// it, and provisional rows that match it over the candle still open.
+    #[test]
+    fn live_updates_match_compute(
+        bars in prop::collection::vec((1u64..4, 1.0f64..1e4), 1..120),
+        periods in prop::collection::vec(1u32..15, 3),
+        split in 0usize..120,
+    ) {
+        // Half-second steps over one-second candles: one or two trades each.
+        let interval = Duration::from_secs(1);
+        let mut trades = Trades::default();
+        let mut timestamp = 1_000_000;
+        for (step, price) in bars {
+            timestamp += step * 500_000;
+            trades.push(timestamp, price, 1.0, Side::Buy);
+        }
+        let split = split.min(trades.len());
+        let prefix = |n: usize| Trades {
+            timestamps: trades.timestamps[..n].to_vec(),
+            prices: trades.prices[..n].to_vec(),
+            quantities: trades.quantities[..n].to_vec(),
+            taker_sides: trades.taker_sides[..n].to_vec(),
+        };
+        let history = store::fold_candles(&prefix(split), interval);
+        let seeded_until = split.checked_sub(1).map_or(0, |i| trades.timestamps[i]);
+        let mut candles = store::fold_candles(&trades, interval);
+        candles.pop();
+
+        let registry = Registry::builtin();
+        for indicator in registry.iter() {
+            let Some(params) = params(indicator, &periods) else {
+                continue;
+            };
+            let rows = compute(indicator, &params, &candles);
+            let warm_up = indicator.warm_up(&params);
+            let update = |i: usize, values: Vec<f64>, candles: &[Candle], provisional| {
+                IndicatorUpdate { timestamp: candles[i].timestamp, values, provisional }
+            };
+            // The last history candle is still open, so the seeded value is
+            // the one before it.
+            let expected: Vec<IndicatorUpdate> = (history.len().saturating_sub(2)..candles.len())
+                .filter(|i| *i >= warm_up)
+                .map(|i| update(i, rows[i - warm_up].clone(), &candles, false))
+                .collect();
+
+            let mut live = LiveIndicator::new(
+                registry.get(indicator.name()).unwrap(),
+                params.clone(),
+                SeriesType::Close,
+                interval,
+                history.clone(),
+                seeded_until,
+            );
+            let mut updates: Vec<IndicatorUpdate> = live.latest().into_iter().collect();
+            for i in split..trades.len() {
+                updates.extend(live.push(&data::Trade {
+                    symbol: "BTCUSDT".to_string(),
+                    price: trades.prices[i],
+                    quantity: 1.0,
+                    exchange: data::trade::Exchange::Binance as i32,
+                    exchange_timestamp: trades.timestamps[i],
+                    ingestion_timestamp: None,
+                    trade_id: i.to_string(),
+                    taker_side: Side::Buy as i32,
+                }));
+                let so_far = store::fold_candles(&prefix(i + 1), interval);
+                let provisional = compute(indicator, &params, &so_far)
+                    .pop()
+                    .map(|values| update(so_far.len() - 1, values, &so_far, true));
+                prop_assert_eq!(live.provisional(), provisional, "{}", indicator.name());
+            }
+            prop_assert_eq!(updates, expected, "{}", indicator.name());
+        }
+    }
+}
+
+/// Checks `indicators::macd` against a golden file written by
+/// `tests/data/generate_macd.py`. Its `# source:` line says whether TA-Lib
+/// computed it or the script's port of `TA_MACD`. The checked-in files still
+/// come from the port, so until they are regenerated with TA-Lib installed
+/// they only pin the Rust code to that port, not to TA-Lib itself.
+fn assert_matches_golden(csv: &str, fast: usize, slow: usize, signal: usize) {
+    let rows: Vec<Vec<&str>> = csv
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .skip(1)
+        .map(|line| line.split(',').collect())
+        .collect();
+    let closes: Vec<f64> = rows.iter().map(|row| row[0].parse().unwrap()).collect();
+    let periods = MacdPeriods::new(fast, slow, signal).unwrap();
+    let macd = indicators::macd(&closes, periods);
+
+    let warm_up = rows.iter().take_while(|row| row[1].is_empty()).count();
+    assert_eq!(warm_up, periods.warm_up());
+    assert_eq!(macd.len(), rows.len() - warm_up);
+    for (i, (row, actual)) in rows[warm_up..].iter().zip(&macd).enumerate() {
+        let expected: Vec<f64> = row[1..].iter().map(|v| v.parse().unwrap()).collect();
+        for (name, actual, expected) in [
+            ("macd", actual.macd, expected[0]),
+            ("signal", actual.signal, expected[1]),
+            ("histogram", actual.histogram, expected[2]),
+        ] {
+            assert!(
+                (actual - expected).abs() < 1e-9,
+                "{name} at row {}: {actual} != {expected}",
+                warm_up + i
+            );
+        }
+    }
+}
+
+#[test]
+fn macd_matches_golden_values_with_default_periods() {
+    assert_matches_golden(include_str!("data/macd_12_26_9.csv"), 12, 26, 9);
+}
+
+#[test]
+fn macd_matches_golden_values_with_short_periods() {
+    assert_matches_golden(include_str!("data/macd_5_13_4.csv"), 5, 13, 4);
+}
+
+#[test]
+fn macd_matches_golden_values_with_a_signal_longer_than_the_slow_period() {
+    assert_matches_golden(include_str!("data/macd_3_10_16.csv"), 3, 10, 16);
+}
+
+#[test]
+fn macd_periods_are_validated() {
+    assert!(MacdPeriods::new(0, 26, 9).is_err());
+    assert!(MacdPeriods::new(12, 26, 0).is_err());
+    assert!(MacdPeriods::new(26, 26, 9).is_err());
+    assert!(MacdPeriods::new(26, 12, 9).is_err());
+    assert_eq!(MacdPeriods::new(12, 26, 9).unwrap().warm_up(), 33);
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use analytics_server::analytics::{GetMovingAverageRequest, MovingAverageDataPoint};
+use analytics_server::paging::Paging;
+
+fn request(page_size: u32, page_token: String) -> GetMovingAverageRequest {
+    GetMovingAverageRequest {
+        symbol: "BTCUSDT".to_string(),
+        window_size: 2,
+        page_size,
+        page_token,
+        ..Default::default()
+    }
+}
+
+fn key(point: &MovingAverageDataPoint) -> (u64, f64) {
+    (point.timestamp, point.value)
+}
+
+/// Values 0, 1, ... at timestamps 1, 2, 2, 2, 2, 3, 4, as trades can share
+/// one.
+fn points() -> Vec<MovingAverageDataPoint> {
+    [1, 2, 2, 2, 2, 3, 4]
+        .into_iter()
+        .enumerate()
+        .map(|(i, timestamp)| MovingAverageDataPoint {
+            timestamp,
+            value: i as f64,
+        })
+        .collect()
+}
+
+/// Values of each page, following the tokens from the first to the last.
+fn pages(page_size: u32) -> Vec<Vec<f64>> {
+    let mut pages = Vec::new();
+    let mut page_token = String::new();
+    loop {
+        let paging = Paging::from_request("sma", &request(page_size, page_token)).unwrap();
+        let (page, next_page_token) = paging.page(points(), key);
+        pages.push(page.iter().map(|point| point.value).collect());
+        if next_page_token.is_empty() {
+            return pages;
+        }
+        page_token = next_page_token;
+    }
+}
+
+#[test]
+fn pages_split_points_that_share_a_timestamp() {
+    assert_eq!(
+        pages(2),
+        vec![vec![0.0, 1.0], vec![2.0, 3.0], vec![4.0, 5.0], vec![6.0]]
+    );
+    assert_eq!(
+        pages(3),
+        vec![vec![0.0, 1.0, 2.0], vec![3.0, 4.0, 5.0], vec![6.0]]
+    );
+    assert_eq!(pages(0), vec![(0..7).map(f64::from).collect::<Vec<_>>()]);
+}
+
+#[test]
+fn chunks_carry_the_same_tokens_as_pages() {
+    let paging = Paging::from_request("sma", &request(3, String::new())).unwrap();
+    let chunks: Vec<(Vec<MovingAverageDataPoint>, String)> = paging.chunks(points(), key).collect();
+    let (first, token) = paging.page(points(), key);
+    assert_eq!((&chunks[0].0, &chunks[0].1), (&first, &token));
+
+    let paging = Paging::from_request("sma", &request(3, token)).unwrap();
+    let rest: Vec<(Vec<MovingAverageDataPoint>, String)> = paging.chunks(points(), key).collect();
+    assert_eq!(rest, chunks[1..]);
+}
+
+#[test]
+fn points_past_the_page_are_never_pulled() {
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let counter = pulled.clone();
+    let points = (0u64..).map(move |i| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        MovingAverageDataPoint {
+            timestamp: i,
+            value: 0.0,
+        }
+    });
+    let paging = Paging::from_request("sma", &request(3, String::new())).unwrap();
+    let (page, next_page_token) = paging.page(points, key);
+    assert_eq!(page.len(), 3);
+    assert!(!next_page_token.is_empty());
+    // The page, and one more point to tell it is not the last.
+    assert_eq!(pulled.load(Ordering::Relaxed), 4);
+}
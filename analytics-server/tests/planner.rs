@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use analytics_server::data::trade::Side;
+use analytics_server::planner::{Plan, Planner};
+use analytics_server::store::memory::MemoryStore;
+use analytics_server::store::table::TradeFilter;
+use analytics_server::store::{Aggregation, Candle, TimeRange, TradeStore, Trades};
+use async_trait::async_trait;
+use datafusion::arrow::array::{AsArray, RecordBatch};
+use datafusion::arrow::compute::concat_batches;
+use datafusion::arrow::datatypes::Int64Type;
+use datafusion::prelude::{col, lit};
+use tonic::Status;
+
+const SYMBOL: &str = "BTCUSDT";
+const ALL: TimeRange = TimeRange {
+    start_micros: 0,
+    end_micros: u64::MAX,
+};
+
+/// Trades spread over several minutes, with both sides and an empty minute.
+fn store() -> MemoryStore {
+    let mut store = MemoryStore::new();
+    for i in 0..200u64 {
+        let minute = if i < 100 { i / 50 } else { i / 50 + 1 };
+        let timestamp = minute * 60_000_000 + (i % 50) * 1_000_000;
+        let side = match i % 3 {
+            0 => Side::Buy,
+            1 => Side::Sell,
+            _ => Side::Unknown,
+        };
+        store.insert(
+            SYMBOL,
+            timestamp,
+            100.0 + (i % 7) as f64 * 0.5,
+            0.1 + (i % 5) as f64,
+            side,
+        );
+    }
+    store.insert("ETHUSDT", 0, 2000.0, 3.0, Side::Buy);
+    store
+}
+
+/// Only serves raw trades, so everything is aggregated in process.
+struct RawStore(MemoryStore);
+
+#[async_trait]
+impl TradeStore for RawStore {
+    async fn trades(&self, symbol: &str, range: TimeRange) -> Result<Trades, Status> {
+        self.0.trades(symbol, range).await
+    }
+
+    async fn symbols(&self) -> Result<Vec<String>, Status> {
+        self.0.symbols().await
+    }
+}
+
+/// Counts the symbols whose trades were read.
+struct CountingStore {
+    store: RawStore,
+    reads: AtomicUsize,
+}
+
+#[async_trait]
+impl TradeStore for CountingStore {
+    async fn trades(&self, symbol: &str, range: TimeRange) -> Result<Trades, Status> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.store.trades(symbol, range).await
+    }
+
+    async fn symbols(&self) -> Result<Vec<String>, Status> {
+        self.store.symbols().await
+    }
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() <= 1e-9 * a.abs().max(1.0), "{a} != {b}");
+}
+
+fn assert_candles_match(a: &[Candle], b: &[Candle]) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert_eq!(a.timestamp, b.timestamp);
+        assert_eq!(a.open, b.open);
+        assert_eq!(a.high, b.high);
+        assert_eq!(a.low, b.low);
+        assert_eq!(a.close, b.close);
+        assert_eq!(a.trade_count, b.trade_count);
+        assert_close(a.volume, b.volume);
+        assert_close(a.quote_volume, b.quote_volume);
+        assert_close(a.buy_volume, b.buy_volume);
+        assert_close(a.sell_volume, b.sell_volume);
+    }
+}
+
+#[tokio::test]
+async fn plans_follow_the_store() {
+    let pushdown = Planner::new(Arc::new(store()));
+    assert_eq!(pushdown.plan(Aggregation::Summary), Plan::Pushdown);
+    assert_eq!(pushdown.plan(Aggregation::Candles), Plan::Pushdown);
+
+    let raw = Planner::new(Arc::new(RawStore(store())));
+    assert_eq!(raw.plan(Aggregation::Summary), Plan::InProcess);
+    assert_eq!(raw.plan(Aggregation::Candles), Plan::InProcess);
+}
+
+#[tokio::test]
+async fn summary_matches_across_plans() {
+    let store: Arc<dyn TradeStore> = Arc::new(store());
+    let pushdown = Planner::forced(store.clone(), Plan::Pushdown)
+        .summary(SYMBOL, ALL)
+        .await
+        .unwrap();
+    let in_process = Planner::forced(store, Plan::InProcess)
+        .summary(SYMBOL, ALL)
+        .await
+        .unwrap();
+
+    assert_eq!(pushdown.trade_count, 200);
+    assert_eq!(pushdown.trade_count, in_process.trade_count);
+    assert_close(pushdown.volume, in_process.volume);
+    assert_close(pushdown.quote_volume, in_process.quote_volume);
+    assert_close(pushdown.vwap(), in_process.vwap());
+}
+
+#[tokio::test]
+async fn candles_match_across_plans() {
+    let store: Arc<dyn TradeStore> = Arc::new(store());
+    for interval in [1, 7, 60, 3600] {
+        let interval = Duration::from_secs(interval);
+        let pushdown = Planner::forced(store.clone(), Plan::Pushdown)
+            .candles(SYMBOL, ALL, interval)
+            .await
+            .unwrap();
+        let in_process = Planner::forced(store.clone(), Plan::InProcess)
+            .candles(SYMBOL, ALL, interval)
+            .await
+            .unwrap();
+        assert!(!pushdown.is_empty());
+        assert_candles_match(&pushdown, &in_process);
+    }
+}
+
+#[tokio::test]
+async fn in_process_plan_handles_empty_ranges() {
+    let planner = Planner::new(Arc::new(RawStore(store())));
+    let summary = planner.summary("SOLUSDT", ALL).await.unwrap();
+    assert_eq!(summary.trade_count, 0);
+    let candles = planner
+        .candles("SOLUSDT", ALL, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(candles.is_empty());
+}
+
+#[tokio::test]
+async fn forcing_pushdown_needs_store_support() {
+    let planner = Planner::forced(Arc::new(RawStore(store())), Plan::Pushdown);
+    let err = planner.summary(SYMBOL, ALL).await.unwrap_err();
+    assert_eq!(err.code(), tonic::Code::Unimplemented);
+}
+
+async fn sql(planner: &Planner, sql: &str) -> RecordBatch {
+    let df = planner.context().sql(sql).await.unwrap();
+    let schema = df.schema().inner().clone();
+    concat_batches(&schema, &df.collect().await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn trades_table_answers_sql() {
+    let planner = Planner::new(Arc::new(RawStore(store())));
+    let batch = sql(
+        &planner,
+        "SELECT symbol, COUNT(*) AS trades FROM trades
+         WHERE exchange_timestamp < 60000000
+         GROUP BY symbol ORDER BY symbol",
+    )
+    .await;
+    let symbols = batch.column(0).as_string::<i32>();
+    let counts = batch.column(1).as_primitive::<Int64Type>();
+    assert_eq!(symbols.value(0), "BTCUSDT");
+    assert_eq!(counts.value(0), 50);
+    assert_eq!(symbols.value(1), "ETHUSDT");
+    assert_eq!(counts.value(1), 1);
+
+    let batch = sql(
+        &planner,
+        "SELECT COUNT(*) FROM trades WHERE symbol IN ('ETHUSDT', 'SOLUSDT')",
+    )
+    .await;
+    assert_eq!(batch.column(0).as_primitive::<Int64Type>().value(0), 1);
+}
+
+#[tokio::test]
+async fn table_scans_stop_reading_once_the_limit_is_met() {
+    let store = Arc::new(CountingStore {
+        store: RawStore(store()),
+        reads: AtomicUsize::new(0),
+    });
+    let planner = Planner::new(store.clone());
+    for table in ["trades", "candles"] {
+        store.reads.store(0, Ordering::Relaxed);
+        let batch = sql(&planner, &format!("SELECT symbol FROM {table} LIMIT 1")).await;
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.column(0).as_string::<i32>().value(0), "BTCUSDT");
+        // ETHUSDT, the second symbol, is never read.
+        assert_eq!(store.reads.load(Ordering::Relaxed), 1, "{table}");
+    }
+}
+
+#[test]
+fn trade_filter_collects_symbol_and_time_conditions() {
+    let filters = [
+        col("symbol").eq(lit("BTCUSDT")),
+        col("exchange_timestamp").gt_eq(lit(10i64)),
+        lit(20i64).gt(col("exchange_timestamp")),
+        col("exchange_timestamp").between(lit(5i64), lit(30i64)),
+    ];
+    let filter = TradeFilter::new(&filters);
+    assert_eq!(filter.symbols, Some(vec!["BTCUSDT".to_string()]));
+    assert_eq!(
+        filter.range(),
+        Some(TimeRange {
+            start_micros: 10,
+            end_micros: 19,
+        })
+    );
+
+    assert!(!TradeFilter::supports(&col("price").gt(lit(1.0))));
+    assert!(!TradeFilter::supports(
+        &col("symbol").not_eq(lit("BTCUSDT"))
+    ));
+
+    let disjoint = [
+        col("symbol").eq(lit("BTCUSDT")),
+        col("symbol").eq(lit("ETHUSDT")),
+    ];
+    assert_eq!(TradeFilter::new(&disjoint).range(), None);
+}
@@ -0,0 +1,24 @@
+-- Raw trades written by clickhouse_sink.
+--
+-- The sink is at-least-once, so a Kafka redelivery inserts the same trade twice.
+-- ReplacingMergeTree collapses rows sharing the sorting key during merges, and
+-- readers use `FINAL` to get deduplicated results before a merge has happened.
+-- A trade is identified by exchange + symbol + trade_id, and a redelivered trade
+-- carries the same exchange_timestamp, so appending (exchange, trade_id) to the
+-- (symbol, exchange_timestamp) order keeps range scans cheap and dedup correct.
+--
+-- exchange_timestamp is in microseconds since the epoch.
+CREATE TABLE IF NOT EXISTS default.trades
+(
+    symbol LowCardinality(String),
+    price Float64 CODEC(Gorilla, ZSTD(1)),
+    quantity Float64 CODEC(Gorilla, ZSTD(1)),
+    exchange_timestamp UInt64 CODEC(DoubleDelta, ZSTD(1)),
+    ingestion_timestamp Nullable(UInt64) CODEC(DoubleDelta, ZSTD(1)),
+    exchange LowCardinality(String),
+    trade_id String CODEC(ZSTD(1))
+)
+ENGINE = ReplacingMergeTree
+PARTITION BY toDate(toDateTime(intDiv(exchange_timestamp, 1000000)))
+ORDER BY (symbol, exchange_timestamp, exchange, trade_id)
+TTL toDateTime(intDiv(exchange_timestamp, 1000000)) + INTERVAL 90 DAY;
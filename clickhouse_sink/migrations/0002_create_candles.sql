@@ -0,0 +1,178 @@
+-- OHLCV candles rolled up from default.trades by materialized views.
+--
+-- Each view fires on every insert into trades and writes partial aggregates;
+-- AggregatingMergeTree folds them together on merge, so readers must finalize with
+-- `argMinMerge(open)`, `argMaxMerge(close)` and GROUP BY the key columns.
+-- VWAP is `quote_volume / volume`.
+--
+-- The views see inserts, not the deduplicated table, so a trade redelivered by the
+-- sink after a crash is counted twice here even though trades FINAL shows it once.
+-- Rows inserted before this migration are not backfilled.
+
+CREATE TABLE IF NOT EXISTS default.candles_1s
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open AggregateFunction(argMin, Float64, UInt64),
+    high SimpleAggregateFunction(max, Float64),
+    low SimpleAggregateFunction(min, Float64),
+    close AggregateFunction(argMax, Float64, UInt64),
+    volume SimpleAggregateFunction(sum, Float64),
+    quote_volume SimpleAggregateFunction(sum, Float64),
+    trade_count SimpleAggregateFunction(sum, UInt64)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toDate(bucket)
+ORDER BY (symbol, exchange, bucket)
+TTL bucket + INTERVAL 7 DAY;
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS default.candles_1s_mv
+TO default.candles_1s
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 SECOND) AS bucket,
+    argMinState(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMaxState(price, exchange_timestamp) AS close,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    toUInt64(count()) AS trade_count
+FROM default.trades
+GROUP BY symbol, exchange, bucket;
+
+CREATE TABLE IF NOT EXISTS default.candles_1m
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open AggregateFunction(argMin, Float64, UInt64),
+    high SimpleAggregateFunction(max, Float64),
+    low SimpleAggregateFunction(min, Float64),
+    close AggregateFunction(argMax, Float64, UInt64),
+    volume SimpleAggregateFunction(sum, Float64),
+    quote_volume SimpleAggregateFunction(sum, Float64),
+    trade_count SimpleAggregateFunction(sum, UInt64)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toDate(bucket)
+ORDER BY (symbol, exchange, bucket)
+TTL bucket + INTERVAL 90 DAY;
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS default.candles_1m_mv
+TO default.candles_1m
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 MINUTE) AS bucket,
+    argMinState(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMaxState(price, exchange_timestamp) AS close,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    toUInt64(count()) AS trade_count
+FROM default.trades
+GROUP BY symbol, exchange, bucket;
+
+CREATE TABLE IF NOT EXISTS default.candles_5m
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open AggregateFunction(argMin, Float64, UInt64),
+    high SimpleAggregateFunction(max, Float64),
+    low SimpleAggregateFunction(min, Float64),
+    close AggregateFunction(argMax, Float64, UInt64),
+    volume SimpleAggregateFunction(sum, Float64),
+    quote_volume SimpleAggregateFunction(sum, Float64),
+    trade_count SimpleAggregateFunction(sum, UInt64)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toDate(bucket)
+ORDER BY (symbol, exchange, bucket)
+TTL bucket + INTERVAL 365 DAY;
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS default.candles_5m_mv
+TO default.candles_5m
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 5 MINUTE) AS bucket,
+    argMinState(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMaxState(price, exchange_timestamp) AS close,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    toUInt64(count()) AS trade_count
+FROM default.trades
+GROUP BY symbol, exchange, bucket;
+
+CREATE TABLE IF NOT EXISTS default.candles_1h
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open AggregateFunction(argMin, Float64, UInt64),
+    high SimpleAggregateFunction(max, Float64),
+    low SimpleAggregateFunction(min, Float64),
+    close AggregateFunction(argMax, Float64, UInt64),
+    volume SimpleAggregateFunction(sum, Float64),
+    quote_volume SimpleAggregateFunction(sum, Float64),
+    trade_count SimpleAggregateFunction(sum, UInt64)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toYYYYMM(bucket)
+ORDER BY (symbol, exchange, bucket);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS default.candles_1h_mv
+TO default.candles_1h
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 HOUR) AS bucket,
+    argMinState(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMaxState(price, exchange_timestamp) AS close,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    toUInt64(count()) AS trade_count
+FROM default.trades
+GROUP BY symbol, exchange, bucket;
+
+CREATE TABLE IF NOT EXISTS default.candles_1d
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open AggregateFunction(argMin, Float64, UInt64),
+    high SimpleAggregateFunction(max, Float64),
+    low SimpleAggregateFunction(min, Float64),
+    close AggregateFunction(argMax, Float64, UInt64),
+    volume SimpleAggregateFunction(sum, Float64),
+    quote_volume SimpleAggregateFunction(sum, Float64),
+    trade_count SimpleAggregateFunction(sum, UInt64)
+)
+ENGINE = AggregatingMergeTree
+PARTITION BY toYYYYMM(bucket)
+ORDER BY (symbol, exchange, bucket);
+
+CREATE MATERIALIZED VIEW IF NOT EXISTS default.candles_1d_mv
+TO default.candles_1d
+AS SELECT
+    symbol,
+    exchange,
+    toDateTime(toStartOfDay(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC')), 'UTC') AS bucket,
+    argMinState(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMaxState(price, exchange_timestamp) AS close,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    toUInt64(count()) AS trade_count
+FROM default.trades
+GROUP BY symbol, exchange, bucket;
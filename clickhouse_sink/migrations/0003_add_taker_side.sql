@@ -0,0 +1,5 @@
+-- Aggressor side of each trade, from Binance's `m` flag and Coinbase's maker
+-- `side`. Rows written before this migration read as SIDE_UNKNOWN.
+
+ALTER TABLE default.trades
+    ADD COLUMN IF NOT EXISTS taker_side LowCardinality(String) DEFAULT 'SIDE_UNKNOWN' AFTER exchange;
@@ -0,0 +1,375 @@
+-- Replaces the insert-triggered candle views of 0002, which counted a trade
+-- redelivered by the sink once per delivery.
+--
+-- Each candles_<width> table is now rebuilt from `trades FINAL`, so every trade
+-- counts once: the migration backfills it, and a refreshable view then recomputes
+-- the buckets from a lookback window onward on a schedule, appending them. Rows
+-- hold finished values, one per (symbol, exchange, bucket) and refresh;
+-- ReplacingMergeTree keeps the latest, so readers use `FINAL`. Buckets are always
+-- recomputed whole. open_time and close_time are the first and last trade's
+-- exchange_timestamp, to combine exchanges with argMin(open, open_time) and
+-- argMax(close, close_time). VWAP is `quote_volume / volume`.
+--
+-- A bucket is settled once no refresh will change it again: after it leaves the
+-- lookback window, less any trade arriving later than that. Readers should take
+-- recent buckets from trades. Late trades reach the rollups only when the sink is
+-- run with --rebuild-candles-from and --rebuild-candles-to over their range.
+--
+--   width  refresh     lookback
+--   1s     1 minute    10 minutes
+--   1m     1 minute    10 minutes
+--   5m     1 minute    15 minutes
+--   1h     5 minutes   2 hours
+--   1d     15 minutes  2 days
+--
+-- Refreshable views need ClickHouse 24.10 or later.
+
+DROP VIEW IF EXISTS default.candles_1s_mv;
+
+DROP TABLE IF EXISTS default.candles_1s;
+
+CREATE TABLE default.candles_1s
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open Float64,
+    high Float64,
+    low Float64,
+    close Float64,
+    open_time UInt64,
+    close_time UInt64,
+    volume Float64,
+    quote_volume Float64,
+    trade_count UInt64,
+    buy_volume Float64,
+    sell_volume Float64,
+    refreshed_at DateTime('UTC')
+)
+ENGINE = ReplacingMergeTree(refreshed_at)
+PARTITION BY toDate(bucket)
+ORDER BY (symbol, exchange, bucket)
+TTL bucket + INTERVAL 7 DAY;
+
+INSERT INTO default.candles_1s
+SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 SECOND) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfDay(now() - INTERVAL 7 DAY))) * 1000000
+GROUP BY symbol, exchange, bucket;
+
+CREATE MATERIALIZED VIEW default.candles_1s_mv
+REFRESH EVERY 1 MINUTE APPEND
+TO default.candles_1s
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 SECOND) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfMinute(now() - INTERVAL 10 MINUTE))) * 1000000
+GROUP BY symbol, exchange, bucket;
+
+DROP VIEW IF EXISTS default.candles_1m_mv;
+
+DROP TABLE IF EXISTS default.candles_1m;
+
+CREATE TABLE default.candles_1m
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open Float64,
+    high Float64,
+    low Float64,
+    close Float64,
+    open_time UInt64,
+    close_time UInt64,
+    volume Float64,
+    quote_volume Float64,
+    trade_count UInt64,
+    buy_volume Float64,
+    sell_volume Float64,
+    refreshed_at DateTime('UTC')
+)
+ENGINE = ReplacingMergeTree(refreshed_at)
+PARTITION BY toDate(bucket)
+ORDER BY (symbol, exchange, bucket)
+TTL bucket + INTERVAL 90 DAY;
+
+INSERT INTO default.candles_1m
+SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 MINUTE) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfDay(now() - INTERVAL 90 DAY))) * 1000000
+GROUP BY symbol, exchange, bucket;
+
+CREATE MATERIALIZED VIEW default.candles_1m_mv
+REFRESH EVERY 1 MINUTE APPEND
+TO default.candles_1m
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 MINUTE) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfMinute(now() - INTERVAL 10 MINUTE))) * 1000000
+GROUP BY symbol, exchange, bucket;
+
+DROP VIEW IF EXISTS default.candles_5m_mv;
+
+DROP TABLE IF EXISTS default.candles_5m;
+
+CREATE TABLE default.candles_5m
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open Float64,
+    high Float64,
+    low Float64,
+    close Float64,
+    open_time UInt64,
+    close_time UInt64,
+    volume Float64,
+    quote_volume Float64,
+    trade_count UInt64,
+    buy_volume Float64,
+    sell_volume Float64,
+    refreshed_at DateTime('UTC')
+)
+ENGINE = ReplacingMergeTree(refreshed_at)
+PARTITION BY toDate(bucket)
+ORDER BY (symbol, exchange, bucket)
+TTL bucket + INTERVAL 365 DAY;
+
+INSERT INTO default.candles_5m
+SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 5 MINUTE) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+GROUP BY symbol, exchange, bucket;
+
+CREATE MATERIALIZED VIEW default.candles_5m_mv
+REFRESH EVERY 1 MINUTE APPEND
+TO default.candles_5m
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 5 MINUTE) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfInterval(now() - INTERVAL 15 MINUTE, INTERVAL 5 MINUTE))) * 1000000
+GROUP BY symbol, exchange, bucket;
+
+DROP VIEW IF EXISTS default.candles_1h_mv;
+
+DROP TABLE IF EXISTS default.candles_1h;
+
+CREATE TABLE default.candles_1h
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open Float64,
+    high Float64,
+    low Float64,
+    close Float64,
+    open_time UInt64,
+    close_time UInt64,
+    volume Float64,
+    quote_volume Float64,
+    trade_count UInt64,
+    buy_volume Float64,
+    sell_volume Float64,
+    refreshed_at DateTime('UTC')
+)
+ENGINE = ReplacingMergeTree(refreshed_at)
+PARTITION BY toYYYYMM(bucket)
+ORDER BY (symbol, exchange, bucket);
+
+INSERT INTO default.candles_1h
+SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 HOUR) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+GROUP BY symbol, exchange, bucket;
+
+CREATE MATERIALIZED VIEW default.candles_1h_mv
+REFRESH EVERY 5 MINUTE APPEND
+TO default.candles_1h
+AS SELECT
+    symbol,
+    exchange,
+    toStartOfInterval(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC'), INTERVAL 1 HOUR) AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfHour(now() - INTERVAL 2 HOUR))) * 1000000
+GROUP BY symbol, exchange, bucket;
+
+DROP VIEW IF EXISTS default.candles_1d_mv;
+
+DROP TABLE IF EXISTS default.candles_1d;
+
+CREATE TABLE default.candles_1d
+(
+    symbol LowCardinality(String),
+    exchange LowCardinality(String),
+    bucket DateTime('UTC'),
+    open Float64,
+    high Float64,
+    low Float64,
+    close Float64,
+    open_time UInt64,
+    close_time UInt64,
+    volume Float64,
+    quote_volume Float64,
+    trade_count UInt64,
+    buy_volume Float64,
+    sell_volume Float64,
+    refreshed_at DateTime('UTC')
+)
+ENGINE = ReplacingMergeTree(refreshed_at)
+PARTITION BY toYYYYMM(bucket)
+ORDER BY (symbol, exchange, bucket);
+
+INSERT INTO default.candles_1d
+SELECT
+    symbol,
+    exchange,
+    toDateTime(toStartOfDay(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC')), 'UTC') AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+GROUP BY symbol, exchange, bucket;
+
+CREATE MATERIALIZED VIEW default.candles_1d_mv
+REFRESH EVERY 15 MINUTE APPEND
+TO default.candles_1d
+AS SELECT
+    symbol,
+    exchange,
+    toDateTime(toStartOfDay(toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC')), 'UTC') AS bucket,
+    argMin(price, exchange_timestamp) AS open,
+    max(price) AS high,
+    min(price) AS low,
+    argMax(price, exchange_timestamp) AS close,
+    min(exchange_timestamp) AS open_time,
+    max(exchange_timestamp) AS close_time,
+    sum(quantity) AS volume,
+    sum(price * quantity) AS quote_volume,
+    count() AS trade_count,
+    sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+    sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+    now() AS refreshed_at
+FROM default.trades FINAL
+WHERE exchange_timestamp >= toUInt64(toUnixTimestamp(toStartOfDay(now() - INTERVAL 2 DAY))) * 1000000
+GROUP BY symbol, exchange, bucket;
@@ -0,0 +1,76 @@
+use rdkafka::message::{BorrowedMessage, Header, Headers, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{ClientConfig, Message};
+use std::time::Duration;
+
+/// Forwards messages the sink cannot process to a dead-letter topic, keeping the
+/// original key, payload and headers and recording why and where they failed.
+pub struct DeadLetterQueue {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl DeadLetterQueue {
+    pub fn new(brokers: &str, topic: &str) -> Result<Self, rdkafka::error::KafkaError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "5000")
+            .create()?;
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+
+    /// Resolves only once the broker has acknowledged the record, so the source
+    /// offset can be committed safely afterwards.
+    pub async fn send(
+        &self,
+        msg: &BorrowedMessage<'_>,
+        error: &str,
+    ) -> Result<(), rdkafka::error::KafkaError> {
+        let partition = msg.partition().to_string();
+        let offset = msg.offset().to_string();
+
+        let mut headers = OwnedHeaders::new();
+        if let Some(original) = msg.headers() {
+            for header in original.iter() {
+                headers = headers.insert(Header {
+                    key: header.key,
+                    value: header.value,
+                });
+            }
+        }
+        let headers = headers
+            .insert(Header {
+                key: "x-error",
+                value: Some(error),
+            })
+            .insert(Header {
+                key: "x-source-topic",
+                value: Some(msg.topic()),
+            })
+            .insert(Header {
+                key: "x-source-partition",
+                value: Some(&partition),
+            })
+            .insert(Header {
+                key: "x-source-offset",
+                value: Some(&offset),
+            });
+
+        let mut record = FutureRecord::<[u8], [u8]>::to(&self.topic).headers(headers);
+        if let Some(key) = msg.key() {
+            record = record.key(key);
+        }
+        if let Some(payload) = msg.payload() {
+            record = record.payload(payload);
+        }
+
+        self.producer
+            .send(record, Duration::from_secs(5))
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| e)
+    }
+}
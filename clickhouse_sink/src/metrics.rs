@@ -0,0 +1,103 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder, exponential_buckets,
+};
+
+pub struct SinkMetrics {
+    registry: Registry,
+    /// Labelled by `backend` and `table`.
+    pub rows_inserted: IntCounterVec,
+    /// Labelled by `result`: `ok`, `retryable_error` or `fatal_error`.
+    pub insert_attempts: IntCounterVec,
+    /// Labelled by `reason`: `decode_error` or `empty_payload`.
+    pub dead_letters: IntCounterVec,
+    /// Labelled by `topic` and `partition`, refreshed from librdkafka statistics.
+    pub consumer_lag: IntGaugeVec,
+    /// Seconds from `exchange_timestamp` until the backend acknowledged the
+    /// trade, labelled by `exchange` and `symbol`.
+    pub end_to_end_latency: HistogramVec,
+    pub batch_size: Histogram,
+    pub assigned_partitions: IntGauge,
+    /// Labelled by `event`: `assign`, `revoke` or `error`.
+    pub rebalances: IntCounterVec,
+}
+
+impl SinkMetrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("clickhouse_sink".to_string()), None)?;
+
+        let rows_inserted = IntCounterVec::new(
+            Opts::new(
+                "rows_inserted_total",
+                "Rows acknowledged by the backend per table",
+            ),
+            &["backend", "table"],
+        )?;
+        let insert_attempts = IntCounterVec::new(
+            Opts::new("insert_attempts_total", "Backend write attempts by result"),
+            &["result"],
+        )?;
+        let dead_letters = IntCounterVec::new(
+            Opts::new(
+                "dead_letters_total",
+                "Kafka messages routed to the dead-letter topic by reason",
+            ),
+            &["reason"],
+        )?;
+        let consumer_lag = IntGaugeVec::new(
+            Opts::new(
+                "consumer_lag",
+                "Messages between the committed offset and the high watermark",
+            ),
+            &["topic", "partition"],
+        )?;
+        let end_to_end_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "end_to_end_latency_seconds",
+                "Time from exchange_timestamp to backend acknowledgement",
+            )
+            .buckets(exponential_buckets(0.01, 2.0, 14)?),
+            &["exchange", "symbol"],
+        )?;
+        let batch_size = Histogram::with_opts(
+            HistogramOpts::new("batch_size", "Trades per flushed batch")
+                .buckets(exponential_buckets(1.0, 2.0, 18)?),
+        )?;
+        let assigned_partitions = IntGauge::new(
+            "assigned_partitions",
+            "Partitions currently assigned to this consumer",
+        )?;
+        let rebalances = IntCounterVec::new(
+            Opts::new("rebalances_total", "Consumer group rebalance events"),
+            &["event"],
+        )?;
+
+        registry.register(Box::new(rows_inserted.clone()))?;
+        registry.register(Box::new(insert_attempts.clone()))?;
+        registry.register(Box::new(dead_letters.clone()))?;
+        registry.register(Box::new(consumer_lag.clone()))?;
+        registry.register(Box::new(end_to_end_latency.clone()))?;
+        registry.register(Box::new(batch_size.clone()))?;
+        registry.register(Box::new(assigned_partitions.clone()))?;
+        registry.register(Box::new(rebalances.clone()))?;
+
+        Ok(Self {
+            registry,
+            rows_inserted,
+            insert_attempts,
+            dead_letters,
+            consumer_lag,
+            end_to_end_latency,
+            batch_size,
+            assigned_partitions,
+            rebalances,
+        })
+    }
+
+    /// Prometheus text exposition of every registered metric.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
@@ -0,0 +1,231 @@
+use clickhouse::{Client, Row};
+use serde::Deserialize;
+
+/// Versioned schema changes, applied in order. Append new files here; never
+/// edit one that has already shipped.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_trades",
+        sql: include_str!("../migrations/0001_create_trades.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "create_candles",
+        sql: include_str!("../migrations/0002_create_candles.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "add_taker_side",
+        sql: include_str!("../migrations/0003_add_taker_side.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "dedup_candles",
+        sql: include_str!("../migrations/0004_dedup_candles.sql"),
+    },
+];
+
+struct Migration {
+    version: u32,
+    name: &'static str,
+    sql: &'static str,
+}
+
+impl Migration {
+    fn statements(&self) -> Vec<String> {
+        statements(self.sql)
+    }
+}
+
+/// The HTTP interface runs one statement per query, so files are split on `;`,
+/// dropping `--` and `/* */` comments. Quoted strings and identifiers are kept
+/// whole, so a `;` or `--` inside one neither splits nor cuts it.
+fn statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                current.push(c);
+                while let Some(next) = chars.next() {
+                    current.push(next);
+                    if next == '\\' {
+                        current.extend(chars.next());
+                    } else if next == c {
+                        // A doubled quote stands for itself.
+                        match chars.next_if_eq(&c) {
+                            Some(quote) => current.push(quote),
+                            None => break,
+                        }
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                while chars.next_if(|&next| next != '\n').is_some() {}
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = None;
+                for next in chars.by_ref() {
+                    if previous == Some('*') && next == '/' {
+                        break;
+                    }
+                    previous = Some(next);
+                }
+                current.push(' ');
+            }
+            ';' => statements.extend(finish(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    statements.extend(finish(&mut current));
+    statements
+}
+
+/// The statement collected so far, unless it is blank.
+fn finish(current: &mut String) -> Option<String> {
+    let statement = current.trim().to_string();
+    current.clear();
+    (!statement.is_empty()).then_some(statement)
+}
+
+/// Creates the bookkeeping tables if needed and applies every migration whose
+/// version has not been recorded yet. Each statement is recorded as it
+/// succeeds, so a migration that failed partway resumes after the last one
+/// that was applied instead of running it again.
+pub async fn run(client: &Client) -> Result<(), clickhouse::error::Error> {
+    client
+        .query(
+            "CREATE TABLE IF NOT EXISTS default.schema_migrations
+             (
+                 version UInt32,
+                 name String,
+                 applied_at DateTime DEFAULT now()
+             )
+             ENGINE = MergeTree
+             ORDER BY version",
+        )
+        .execute()
+        .await?;
+    client
+        .query(
+            "CREATE TABLE IF NOT EXISTS default.schema_migration_statements
+             (
+                 version UInt32,
+                 statement UInt32,
+                 applied_at DateTime DEFAULT now()
+             )
+             ENGINE = MergeTree
+             ORDER BY (version, statement)",
+        )
+        .execute()
+        .await?;
+
+    #[derive(Debug, Deserialize, Row)]
+    struct Applied {
+        version: u32,
+    }
+    let applied: Vec<u32> = client
+        .query("SELECT version FROM default.schema_migrations")
+        .fetch_all::<Applied>()
+        .await?
+        .into_iter()
+        .map(|row| row.version)
+        .collect();
+
+    #[derive(Debug, Deserialize, Row)]
+    struct Statement {
+        version: u32,
+        statement: u32,
+    }
+    let statements: Vec<Statement> = client
+        .query("SELECT version, statement FROM default.schema_migration_statements")
+        .fetch_all()
+        .await?;
+
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+        println!(
+            "Applying migration {:04}_{}",
+            migration.version, migration.name
+        );
+        for (index, statement) in (0..).zip(migration.statements()) {
+            let done = statements
+                .iter()
+                .any(|s| s.version == migration.version && s.statement == index);
+            if done {
+                println!("Skipping statement {index}, applied by an earlier run");
+                continue;
+            }
+            // Migrations bind nothing, so a `?` in one is literal.
+            client
+                .query(&statement.replace('?', "??"))
+                .execute()
+                .await?;
+            client
+                .query(
+                    "INSERT INTO default.schema_migration_statements (version, statement)
+                     VALUES (?, ?)",
+                )
+                .bind(migration.version)
+                .bind(index)
+                .execute()
+                .await?;
+        }
+        client
+            .query("INSERT INTO default.schema_migrations (version, name) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute()
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::statements;
+
+    #[test]
+    fn splits_on_semicolons_and_drops_comments() {
+        let sql =
+            "-- header\nCREATE TABLE a (x UInt8); -- trailing\n\n/* block;\n */ DROP TABLE b;\n";
+        assert_eq!(
+            statements(sql),
+            vec!["CREATE TABLE a (x UInt8)", "DROP TABLE b"]
+        );
+    }
+
+    #[test]
+    fn keeps_quoted_text_whole() {
+        let sql = "SELECT 'a;b', 'c -- d', \"e;f\", `g--h`; SELECT 'it''s;', 'x\\';y'";
+        assert_eq!(
+            statements(sql),
+            vec![
+                "SELECT 'a;b', 'c -- d', \"e;f\", `g--h`",
+                "SELECT 'it''s;', 'x\\';y'",
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_statements_are_dropped() {
+        assert_eq!(statements(";; -- only a comment\n ;"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn every_migration_splits() {
+        for migration in super::MIGRATIONS {
+            let statements = migration.statements();
+            assert!(!statements.is_empty(), "{}", migration.name);
+            for statement in statements {
+                assert!(!statement.contains("--"), "{statement}");
+            }
+        }
+    }
+}
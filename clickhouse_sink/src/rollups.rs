@@ -0,0 +1,54 @@
+//! Rebuilds the candle rollups of migration 0004 over a time range. Their
+//! refreshable views only recompute a short lookback window, so trades that
+//! land later than that, after a sink outage or a replay, reach the rollups
+//! only through a rebuild.
+
+use clickhouse::Client;
+
+/// Each rollup table, with its bucket width as a ClickHouse interval.
+const ROLLUPS: &[(&str, &str)] = &[
+    ("default.candles_1s", "1 SECOND"),
+    ("default.candles_1m", "1 MINUTE"),
+    ("default.candles_5m", "5 MINUTE"),
+    ("default.candles_1h", "1 HOUR"),
+    ("default.candles_1d", "1 DAY"),
+];
+
+/// Recomputes, from `trades FINAL`, every bucket of every rollup that
+/// overlaps `[from, to]`, in seconds since the epoch. The new rows carry a
+/// later `refreshed_at`, so they replace the old ones.
+pub async fn rebuild(client: &Client, from: u64, to: u64) -> Result<(), clickhouse::error::Error> {
+    for (table, width) in ROLLUPS {
+        println!("Rebuilding {table} from {from} to {to}");
+        let bucket =
+            |expr: &str| format!("toDateTime(toStartOfInterval({expr}, INTERVAL {width}), 'UTC')");
+        let sql = format!(
+            "INSERT INTO {table}
+             SELECT
+                 symbol,
+                 exchange,
+                 {} AS bucket,
+                 argMin(price, exchange_timestamp) AS open,
+                 max(price) AS high,
+                 min(price) AS low,
+                 argMax(price, exchange_timestamp) AS close,
+                 min(exchange_timestamp) AS open_time,
+                 max(exchange_timestamp) AS close_time,
+                 sum(quantity) AS volume,
+                 sum(price * quantity) AS quote_volume,
+                 count() AS trade_count,
+                 sumIf(quantity, taker_side = 'BUY') AS buy_volume,
+                 sumIf(quantity, taker_side = 'SELL') AS sell_volume,
+                 now() AS refreshed_at
+             FROM default.trades FINAL
+             WHERE exchange_timestamp >= toUInt64(toUnixTimestamp({})) * 1000000
+               AND exchange_timestamp < toUInt64(toUnixTimestamp({} + INTERVAL {width})) * 1000000
+             GROUP BY symbol, exchange, bucket",
+            bucket("toDateTime(intDiv(exchange_timestamp, 1000000), 'UTC')"),
+            bucket("toDateTime(?, 'UTC')"),
+            bucket("toDateTime(?, 'UTC')"),
+        );
+        client.query(&sql).bind(from).bind(to).execute().await?;
+    }
+    Ok(())
+}
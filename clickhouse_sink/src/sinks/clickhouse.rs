@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use clickhouse::Row;
+use clickhouse::insert::Insert;
+use serde::{Deserialize, Serialize};
+
+use super::{SinkError, TradeSink, trade_id};
+use crate::{data, migrations, rollups};
+
+#[derive(Debug, Clone, Deserialize, Serialize, Row)]
+struct Trade {
+    symbol: String,
+    price: f64,
+    quantity: f64,
+    exchange_timestamp: u64,
+    /// nanoseconds
+    ingestion_timestamp: Option<u64>,
+    exchange: String,
+    /// Part of the `ReplacingMergeTree` key, so redelivered trades collapse into one row.
+    trade_id: String,
+    /// `BUY`, `SELL` or `SIDE_UNKNOWN`.
+    taker_side: String,
+}
+
+impl From<data::Trade> for Trade {
+    fn from(value: data::Trade) -> Self {
+        let exchange: String = data::trade::Exchange::try_from(value.exchange)
+            .unwrap_or_default()
+            .as_str_name()
+            .to_string();
+        let trade_id = trade_id(&value);
+        let taker_side = data::trade::Side::try_from(value.taker_side)
+            .unwrap_or_default()
+            .as_str_name()
+            .to_string();
+        Self {
+            symbol: value.symbol,
+            price: value.price as f64,
+            quantity: value.quantity as f64,
+            exchange_timestamp: value.exchange_timestamp,
+            ingestion_timestamp: value.ingestion_timestamp.map(|t| {
+                let seconds_as_nanos = t.seconds as u64 * 1_000_000_000;
+
+                let nanos = t.nanos as u64;
+
+                seconds_as_nanos + nanos
+            }),
+            exchange,
+            trade_id,
+            taker_side,
+        }
+    }
+}
+
+impl From<Trade> for data::Trade {
+    fn from(value: Trade) -> Self {
+        Self {
+            symbol: value.symbol,
+            price: value.price,
+            quantity: value.quantity,
+            exchange_timestamp: value.exchange_timestamp,
+            exchange: data::trade::Exchange::from_str_name(&value.exchange)
+                .unwrap_or_default()
+                .into(),
+            ingestion_timestamp: value.ingestion_timestamp.map(|t| prost_types::Timestamp {
+                seconds: t as i64 / 1_000_000_000,
+                nanos: (t % 1_000_000_000) as i32,
+            }),
+            trade_id: value.trade_id,
+            taker_side: data::trade::Side::from_str_name(&value.taker_side)
+                .unwrap_or_default()
+                .into(),
+        }
+    }
+}
+
+/// Network failures and timeouts are worth retrying; anything else (schema
+/// mismatch, bad query) will fail the same way again.
+impl From<clickhouse::error::Error> for SinkError {
+    fn from(error: clickhouse::error::Error) -> Self {
+        match error {
+            clickhouse::error::Error::Network(_) | clickhouse::error::Error::TimedOut => {
+                SinkError::transient(error)
+            }
+            _ => SinkError::fatal(error),
+        }
+    }
+}
+
+pub struct ClickHouseSink {
+    client: clickhouse::Client,
+}
+
+impl ClickHouseSink {
+    /// Connects and brings the schema up to date before any trade is written.
+    pub async fn connect(url: &str) -> Result<Self, clickhouse::error::Error> {
+        let client = clickhouse::Client::default()
+            .with_url(url)
+            .with_database("default");
+
+        migrations::run(&client).await?;
+        println!("ClickHouse schema is up to date");
+
+        Ok(Self { client })
+    }
+
+    /// See [`rollups::rebuild`].
+    pub async fn rebuild_candles(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<(), clickhouse::error::Error> {
+        rollups::rebuild(&self.client, from, to).await
+    }
+}
+
+#[async_trait]
+impl TradeSink for ClickHouseSink {
+    fn name(&self) -> &'static str {
+        "ClickHouse"
+    }
+
+    fn table(&self) -> &'static str {
+        "default.trades"
+    }
+
+    async fn write_batch(&self, trades: &[data::Trade]) -> Result<(), SinkError> {
+        let mut inserter: Insert<Trade> = self.client.insert("trades")?;
+        for trade in trades {
+            inserter.write(&trade.clone().into()).await?;
+        }
+        inserter.end().await?;
+        Ok(())
+    }
+
+    /// Inserts are acknowledged once `Insert::end` returns, so there is nothing
+    /// left to flush.
+    async fn flush(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<(), SinkError> {
+        self.client.query("SELECT 1").execute().await?;
+        Ok(())
+    }
+}
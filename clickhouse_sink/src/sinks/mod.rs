@@ -0,0 +1,82 @@
+pub mod clickhouse;
+pub mod parquet;
+pub mod postgres;
+
+use async_trait::async_trait;
+use std::fmt;
+
+use crate::data;
+
+/// Error returned by a [`TradeSink`]. Transient errors are retried with backoff
+/// by the consumer loop; anything else stops the sink without committing.
+#[derive(Debug)]
+pub struct SinkError {
+    transient: bool,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl SinkError {
+    pub fn transient(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self {
+            transient: true,
+            source: source.into(),
+        }
+    }
+
+    pub fn fatal(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self {
+            transient: false,
+            source: source.into(),
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.source, f)
+    }
+}
+
+impl std::error::Error for SinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// The identifier backends deduplicate on. Trades published before ids were
+/// carried get a deterministic stand-in, so replaying them still deduplicates.
+pub fn trade_id(trade: &data::Trade) -> String {
+    if trade.trade_id.is_empty() {
+        format!(
+            "{}-{}-{}",
+            trade.exchange_timestamp, trade.price, trade.quantity
+        )
+    } else {
+        trade.trade_id.clone()
+    }
+}
+
+/// A storage backend fed by the Kafka consumer loop. The loop owns batching and
+/// offset management; once `write_batch` and `flush` both succeed, the offsets of
+/// every trade in the batch are committed.
+#[async_trait]
+pub trait TradeSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Table (or dataset) the trades land in, used to label metrics.
+    fn table(&self) -> &'static str;
+
+    /// Writes one batch. It may be called again with the same trades after a
+    /// transient error, so implementations should be idempotent where possible.
+    async fn write_batch(&self, trades: &[data::Trade]) -> Result<(), SinkError>;
+
+    /// Makes everything written so far durable.
+    async fn flush(&self) -> Result<(), SinkError>;
+
+    /// Checks the backend is reachable and ready to accept writes.
+    async fn health(&self) -> Result<(), SinkError>;
+}
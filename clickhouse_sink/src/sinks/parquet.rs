@@ -0,0 +1,238 @@
+use arrow::array::{Float64Array, RecordBatch, StringArray, TimestampNanosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::{SinkError, TradeSink, trade_id};
+use crate::data;
+
+/// One line of `manifest.jsonl`, appended after the file it describes is
+/// complete. A batch written again after a failure rewrites the same path, which
+/// is recorded once.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestEntry {
+    path: String,
+    symbol: String,
+    date: NaiveDate,
+    /// Microseconds since the epoch, inclusive.
+    min_exchange_timestamp: u64,
+    max_exchange_timestamp: u64,
+    rows: usize,
+}
+
+/// Writes trades as Parquet under `<root>/date=YYYY-MM-DD/symbol=<SYMBOL>/`,
+/// one file per (date, symbol) per flush, using the same columns the analytics
+/// server registers with DataFusion plus each trade's exchange and id.
+///
+/// Files are never rewritten to drop trades Kafka redelivers, so the same
+/// trade can land in several files; readers deduplicate on
+/// `(exchange, trade_id)`.
+pub struct ParquetSink {
+    root: PathBuf,
+    schema: SchemaRef,
+    /// Paths already in the manifest.
+    recorded: Mutex<HashSet<String>>,
+}
+
+impl ParquetSink {
+    pub fn new(root: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        let recorded = Mutex::new(recorded_paths(&root.join("manifest.jsonl"))?);
+        Ok(Self {
+            root,
+            schema: trade_schema(),
+            recorded,
+        })
+    }
+
+    fn write(
+        &self,
+        trades: &[data::Trade],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut partitions: BTreeMap<(NaiveDate, &str), Vec<&data::Trade>> = BTreeMap::new();
+        for trade in trades {
+            let ts = trade.exchange_timestamp;
+            let date = DateTime::from_timestamp_micros(ts as i64)
+                .ok_or_else(|| format!("Invalid exchange_timestamp {ts}"))?
+                .date_naive();
+            partitions
+                .entry((date, trade.symbol.as_str()))
+                .or_default()
+                .push(trade);
+        }
+
+        for ((date, symbol), mut trades) in partitions {
+            trades.sort_by_key(|t| t.exchange_timestamp);
+            let entry = self.write_partition(date, symbol, &trades)?;
+            self.append_manifest(&entry)?;
+            println!("Archived {} trades to {}", entry.rows, entry.path);
+        }
+        Ok(())
+    }
+
+    fn write_partition(
+        &self,
+        date: NaiveDate,
+        symbol: &str,
+        trades: &[&data::Trade],
+    ) -> Result<ManifestEntry, Box<dyn std::error::Error + Send + Sync>> {
+        let min_exchange_timestamp = trades.first().map_or(0, |t| t.exchange_timestamp);
+        let max_exchange_timestamp = trades.last().map_or(0, |t| t.exchange_timestamp);
+
+        let dir = self
+            .root
+            .join(format!("date={date}"))
+            .join(format!("symbol={symbol}"));
+        fs::create_dir_all(&dir)?;
+        let file_name = format!("part-{min_exchange_timestamp}-{max_exchange_timestamp}.parquet");
+        let path = dir.join(&file_name);
+        let tmp_path = dir.join(format!(".{file_name}.tmp"));
+
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(TimestampNanosecondArray::from_iter_values(
+                    trades.iter().map(|t| t.exchange_timestamp as i64 * 1000),
+                )),
+                Arc::new(Float64Array::from_iter_values(
+                    trades.iter().map(|t| t.price),
+                )),
+                Arc::new(Float64Array::from_iter_values(
+                    trades.iter().map(|t| t.quantity),
+                )),
+                Arc::new(StringArray::from_iter_values(trades.iter().map(|t| {
+                    data::trade::Side::try_from(t.taker_side)
+                        .unwrap_or_default()
+                        .as_str_name()
+                }))),
+                Arc::new(StringArray::from_iter_values(trades.iter().map(|t| {
+                    data::trade::Exchange::try_from(t.exchange)
+                        .unwrap_or_default()
+                        .as_str_name()
+                }))),
+                Arc::new(StringArray::from_iter_values(
+                    trades.iter().map(|t| trade_id(t)),
+                )),
+            ],
+        )?;
+
+        // Written under a temporary name so readers never see a half-written file.
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&tmp_path)?, self.schema.clone(), Some(props))?;
+        writer.write(&batch)?;
+        writer.close()?;
+        fs::rename(&tmp_path, &path)?;
+
+        Ok(ManifestEntry {
+            path: relative(&self.root, &path),
+            symbol: symbol.to_string(),
+            date,
+            min_exchange_timestamp,
+            max_exchange_timestamp,
+            rows: trades.len(),
+        })
+    }
+
+    fn append_manifest(
+        &self,
+        entry: &ManifestEntry,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut recorded = self.recorded.lock().unwrap();
+        if recorded.contains(&entry.path) {
+            return Ok(());
+        }
+        let mut manifest = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.root.join("manifest.jsonl"))?;
+        writeln!(manifest, "{}", serde_json::to_string(entry)?)?;
+        manifest.sync_data()?;
+        recorded.insert(entry.path.clone());
+        Ok(())
+    }
+}
+
+/// The paths `manifest` lists; none when it does not exist yet.
+fn recorded_paths(manifest: &Path) -> std::io::Result<HashSet<String>> {
+    let file = match File::open(manifest) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+    let mut paths = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let entry: ManifestEntry = serde_json::from_str(&line?)?;
+        paths.insert(entry.path);
+    }
+    Ok(paths)
+}
+
+/// The columns of `AnalyticsServiceHandler::build_record_batch` in
+/// analytics-server, then the trade's exchange and id.
+fn trade_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ),
+        Field::new("price", DataType::Float64, false),
+        Field::new("quantity", DataType::Float64, false),
+        // `BUY`, `SELL` or `SIDE_UNKNOWN`.
+        Field::new("taker_side", DataType::Utf8, false),
+        // E.g. `BINANCE`; with `trade_id`, identifies a trade across files.
+        Field::new("exchange", DataType::Utf8, false),
+        Field::new("trade_id", DataType::Utf8, false),
+    ]))
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[async_trait]
+impl TradeSink for ParquetSink {
+    fn name(&self) -> &'static str {
+        "Parquet"
+    }
+
+    fn table(&self) -> &'static str {
+        "trades"
+    }
+
+    async fn write_batch(&self, trades: &[data::Trade]) -> Result<(), SinkError> {
+        tokio::task::block_in_place(|| self.write(trades)).map_err(SinkError::fatal)
+    }
+
+    /// Every file is closed and renamed into place by `write_batch`.
+    async fn flush(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<(), SinkError> {
+        let metadata = fs::metadata(&self.root).map_err(SinkError::fatal)?;
+        if metadata.permissions().readonly() {
+            return Err(SinkError::fatal(format!(
+                "{} is read-only",
+                self.root.display()
+            )));
+        }
+        Ok(())
+    }
+}
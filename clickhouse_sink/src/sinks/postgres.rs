@@ -0,0 +1,198 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::{Client, NoTls};
+
+use super::{SinkError, TradeSink, trade_id};
+use crate::data;
+
+/// `taker_side` is `BUY`, `SELL` or `SIDE_UNKNOWN`; tables created before it
+/// existed gain the column, reading as `SIDE_UNKNOWN` for older rows.
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS trades (
+    symbol TEXT NOT NULL,
+    exchange TEXT NOT NULL,
+    trade_id TEXT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    exchange_timestamp TIMESTAMPTZ NOT NULL,
+    ingestion_timestamp TIMESTAMPTZ,
+    taker_side TEXT NOT NULL DEFAULT 'SIDE_UNKNOWN',
+    PRIMARY KEY (exchange, symbol, trade_id, exchange_timestamp)
+);
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS taker_side TEXT NOT NULL DEFAULT 'SIDE_UNKNOWN'";
+
+/// Redelivered trades hit the primary key and are skipped, mirroring the
+/// `ReplacingMergeTree` dedup on the ClickHouse side.
+const INSERT_TRADES: &str = "INSERT INTO trades
+    (symbol, exchange, trade_id, price, quantity, exchange_timestamp, ingestion_timestamp,
+     taker_side)
+    SELECT * FROM UNNEST(
+        $1::TEXT[], $2::TEXT[], $3::TEXT[], $4::DOUBLE PRECISION[],
+        $5::DOUBLE PRECISION[], $6::TIMESTAMPTZ[], $7::TIMESTAMPTZ[], $8::TEXT[]
+    )
+    ON CONFLICT DO NOTHING";
+
+/// Connection failures and server-side resource or serialization errors may
+/// succeed on retry; constraint and schema errors will not. A closed
+/// connection is reopened by the next call.
+impl From<tokio_postgres::Error> for SinkError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        let transient = match error.code() {
+            None => true,
+            Some(code) => {
+                *code == SqlState::T_R_SERIALIZATION_FAILURE
+                    || *code == SqlState::T_R_DEADLOCK_DETECTED
+                    || *code == SqlState::ADMIN_SHUTDOWN
+                    || *code == SqlState::CANNOT_CONNECT_NOW
+                    || code.code().starts_with("08")
+                    || code.code().starts_with("53")
+            }
+        };
+        if transient {
+            SinkError::transient(error)
+        } else {
+            SinkError::fatal(error)
+        }
+    }
+}
+
+/// Writes trades to PostgreSQL, turning the table into a hypertable partitioned
+/// on `exchange_timestamp` when the TimescaleDB extension is installed.
+pub struct PostgresSink {
+    url: String,
+    /// Replaced with a new connection once the server closes this one.
+    client: Mutex<Client>,
+}
+
+impl PostgresSink {
+    pub async fn connect(url: &str) -> Result<Self, tokio_postgres::Error> {
+        let client = open(url).await?;
+
+        client.batch_execute(CREATE_TABLE).await?;
+        let timescale = client
+            .query_opt(
+                "SELECT 1 FROM pg_extension WHERE extname = 'timescaledb'",
+                &[],
+            )
+            .await?
+            .is_some();
+        if timescale {
+            client
+                .execute(
+                    "SELECT create_hypertable('trades', 'exchange_timestamp', if_not_exists => TRUE)",
+                    &[],
+                )
+                .await?;
+            println!("PostgreSQL trades table is a TimescaleDB hypertable");
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            client: Mutex::new(client),
+        })
+    }
+
+    /// The client, reconnecting first if the connection has closed.
+    async fn client(&self) -> Result<MutexGuard<'_, Client>, SinkError> {
+        let mut client = self.client.lock().await;
+        if client.is_closed() {
+            println!("PostgreSQL connection closed, reconnecting");
+            *client = open(&self.url).await?;
+        }
+        Ok(client)
+    }
+}
+
+async fn open(url: &str) -> Result<Client, tokio_postgres::Error> {
+    let (client, connection) = tokio_postgres::connect(url, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("PostgreSQL connection error: {e}");
+        }
+    });
+    Ok(client)
+}
+
+/// A timestamp PostgreSQL can't store would otherwise be written as some
+/// other time, so it stops the sink instead.
+fn from_micros(micros: u64) -> Result<DateTime<Utc>, SinkError> {
+    i64::try_from(micros)
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .ok_or_else(|| SinkError::fatal(format!("exchange_timestamp {micros} is out of range")))
+}
+
+#[async_trait]
+impl TradeSink for PostgresSink {
+    fn name(&self) -> &'static str {
+        "PostgreSQL"
+    }
+
+    fn table(&self) -> &'static str {
+        "trades"
+    }
+
+    async fn write_batch(&self, trades: &[data::Trade]) -> Result<(), SinkError> {
+        let symbols: Vec<&str> = trades.iter().map(|t| t.symbol.as_str()).collect();
+        let exchanges: Vec<&str> = trades
+            .iter()
+            .map(|t| {
+                data::trade::Exchange::try_from(t.exchange)
+                    .unwrap_or_default()
+                    .as_str_name()
+            })
+            .collect();
+        let trade_ids: Vec<String> = trades.iter().map(trade_id).collect();
+        let prices: Vec<f64> = trades.iter().map(|t| t.price).collect();
+        let quantities: Vec<f64> = trades.iter().map(|t| t.quantity).collect();
+        let exchange_timestamps: Vec<DateTime<Utc>> = trades
+            .iter()
+            .map(|t| from_micros(t.exchange_timestamp))
+            .collect::<Result<_, _>>()?;
+        let ingestion_timestamps: Vec<Option<DateTime<Utc>>> = trades
+            .iter()
+            .map(|t| {
+                t.ingestion_timestamp
+                    .as_ref()
+                    .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32))
+            })
+            .collect();
+        let taker_sides: Vec<&str> = trades
+            .iter()
+            .map(|t| {
+                data::trade::Side::try_from(t.taker_side)
+                    .unwrap_or_default()
+                    .as_str_name()
+            })
+            .collect();
+
+        self.client()
+            .await?
+            .execute(
+                INSERT_TRADES,
+                &[
+                    &symbols,
+                    &exchanges,
+                    &trade_ids,
+                    &prices,
+                    &quantities,
+                    &exchange_timestamps,
+                    &ingestion_timestamps,
+                    &taker_sides,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Each batch is a single autocommitted statement.
+    async fn flush(&self) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    async fn health(&self) -> Result<(), SinkError> {
+        self.client().await?.simple_query("SELECT 1").await?;
+        Ok(())
+    }
+}
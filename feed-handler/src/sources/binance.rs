@@ -21,6 +21,11 @@ struct BinanceTrade {
     quantity: String,
     #[serde(rename(deserialize = "T"))]
     timestamp: u64,
+    #[serde(rename(deserialize = "t"))]
+    trade_id: u64,
+    /// The buyer was the resting order, so the taker sold.
+    #[serde(rename(deserialize = "m"))]
+    is_buyer_maker: bool,
 }
 
 impl From<BinanceTrade> for crate::data::Trade {
@@ -38,6 +43,12 @@ impl From<BinanceTrade> for crate::data::Trade {
                 seconds,
                 nanos: nanos as i32,
             }),
+            trade_id: value.trade_id.to_string(),
+            taker_side: if value.is_buyer_maker {
+                data::trade::Side::Sell.into()
+            } else {
+                data::trade::Side::Buy.into()
+            },
         }
     }
 }
@@ -27,6 +27,9 @@ struct CoinbaseMatch {
     price: String,
     size: String,
     time: DateTime<Utc>,
+    trade_id: u64,
+    /// Side of the maker order; the taker is on the other side.
+    side: String,
 }
 
 impl From<CoinbaseMatch> for crate::data::Trade {
@@ -41,6 +44,12 @@ impl From<CoinbaseMatch> for crate::data::Trade {
                 seconds: value.time.timestamp(),
                 nanos: value.time.timestamp_subsec_nanos() as i32,
             }),
+            trade_id: value.trade_id.to_string(),
+            taker_side: match value.side.as_str() {
+                "buy" => data::trade::Side::Sell.into(),
+                "sell" => data::trade::Side::Buy.into(),
+                _ => data::trade::Side::Unknown.into(),
+            },
         }
     }
 }
@@ -1,33 +1,105 @@
+mod replay;
+
+mod data {
+    include!(concat!(env!("OUT_DIR"), "/data.rs"));
+}
+
+use clap::{Parser, Subcommand};
 use futures_util::StreamExt;
 use rdkafka::ClientConfig;
 use rdkafka::producer::{FutureProducer, FutureRecord};
+use replay::{ReplayConfig, StartPosition};
 use std::time::Duration;
 
+const KAFKA_BROKERS: &str = "localhost:9092";
+const KAFKA_TOPIC: &str = "trades";
+const NATS_URL: &str = "nats://localhost:4222";
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Forward live trades from NATS to Kafka (default)
+    Forward,
+    /// Republish Kafka history onto NATS
+    Replay {
+        /// Start from this offset on every partition
+        #[arg(long, conflicts_with = "from_timestamp")]
+        from_offset: Option<i64>,
+        /// Start from the first record at or after this unix timestamp (seconds)
+        #[arg(long)]
+        from_timestamp: Option<i64>,
+        /// Publish under `<prefix>.trades.*.*`, away from the live subjects
+        /// the bridge forwards back into Kafka
+        #[arg(long, default_value = "replay", conflicts_with = "live_subjects")]
+        prefix: String,
+        /// Publish on the live `trades.*.*` subjects instead. A running
+        /// bridge forwards them into Kafka again.
+        #[arg(long)]
+        live_subjects: bool,
+        /// Playback speed relative to the original pace, 0 for as fast as possible
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let kafka_brokers = "localhost:9092";
-    let kafka_topic = "trades";
+    let cli = Cli::parse();
+
+    match cli.command.unwrap_or(Commands::Forward) {
+        Commands::Forward => forward().await,
+        Commands::Replay {
+            from_offset,
+            from_timestamp,
+            prefix,
+            live_subjects,
+            speed,
+        } => {
+            let start = match (from_offset, from_timestamp) {
+                (Some(offset), _) => StartPosition::Offset(offset),
+                (None, Some(seconds)) => StartPosition::Timestamp(seconds * 1000),
+                (None, None) => StartPosition::Beginning,
+            };
+            replay::run(ReplayConfig {
+                kafka_brokers: KAFKA_BROKERS.to_string(),
+                kafka_topic: KAFKA_TOPIC.to_string(),
+                nats_url: NATS_URL.to_string(),
+                start,
+                subject_prefix: (!live_subjects).then_some(prefix),
+                speed,
+            })
+            .await
+        }
+    }
+}
 
+async fn forward() -> Result<(), Box<dyn std::error::Error>> {
     let producer: FutureProducer = ClientConfig::new()
-        .set("bootstrap.servers", kafka_brokers)
+        .set("bootstrap.servers", KAFKA_BROKERS)
         .set("message.timeout.ms", "5000")
         .create()?;
 
-    println!("Connected to Kafka brokers at {}", kafka_brokers);
+    println!("Connected to Kafka brokers at {}", KAFKA_BROKERS);
 
-    let nats_url = "nats://localhost:4222";
     let nats_subject = "trades.*.*";
-    let nats_client = async_nats::connect(nats_url).await?;
+    let nats_client = async_nats::connect(NATS_URL).await?;
 
     let mut subscription = nats_client.subscribe(nats_subject.to_string()).await?;
 
     while let Some(msg) = subscription.next().await {
-        let record = FutureRecord::to(kafka_topic)
+        // The subject is kept as the key so replays can republish to the same subject.
+        let record = FutureRecord::to(KAFKA_TOPIC)
             .payload(&msg.payload[..])
-            .key("trade");
+            .key(msg.subject.as_str());
 
         match producer.send(record, Duration::from_secs(0)).await {
-            Ok(_) => println!("Message forwarded from NATS to Kafka topic: '{kafka_topic}'"),
+            Ok(_) => println!("Message forwarded from NATS to Kafka topic: '{KAFKA_TOPIC}'"),
             Err((e, _)) => eprintln!("Error sending message: {e}"),
         }
     }
@@ -0,0 +1,209 @@
+use prost::Message as _;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::data;
+
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where in the topic the replay should begin.
+#[derive(Debug, Clone, Copy)]
+pub enum StartPosition {
+    Beginning,
+    Offset(i64),
+    /// Unix timestamp in milliseconds.
+    Timestamp(i64),
+}
+
+pub struct ReplayConfig {
+    pub kafka_brokers: String,
+    pub kafka_topic: String,
+    pub nats_url: String,
+    pub start: StartPosition,
+    /// Prepended to every subject, e.g. `replay` turns `trades.binance.btcusdt`
+    /// into `replay.trades.binance.btcusdt`.
+    pub subject_prefix: Option<String>,
+    /// Playback speed relative to the original pace. `0` replays as fast as possible.
+    pub speed: f64,
+}
+
+/// A record waiting to be republished.
+struct Pending {
+    /// Kafka timestamp in milliseconds.
+    timestamp: Option<i64>,
+    subject: String,
+    payload: Vec<u8>,
+}
+
+/// Reads `kafka_topic` from the requested position up to the high watermark
+/// observed at startup and republishes every record onto NATS.
+///
+/// The bridge spreads the topic across partitions by subject, and Kafka
+/// interleaves partitions arbitrarily, so records are merged by timestamp:
+/// each partition's records are queued until every partition still being
+/// read has one, then the earliest is published. Pacing follows that merged
+/// order.
+pub async fn run(config: ReplayConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let consumer: StreamConsumer = ClientConfig::new()
+        .set("group.id", "nats_replay_group")
+        .set("bootstrap.servers", &config.kafka_brokers)
+        .set("enable.partition.eof", "false")
+        .set("enable.auto.commit", "false")
+        .create()?;
+
+    let mut remaining = assign_partitions(&consumer, &config.kafka_topic, config.start)?;
+    println!(
+        "Replaying topic '{}' across {} partition(s)",
+        config.kafka_topic,
+        remaining.len()
+    );
+
+    let nats_client = async_nats::connect(&config.nats_url).await?;
+    println!("Connected to NATS at {}", config.nats_url);
+
+    let mut queues: HashMap<i32, VecDeque<Pending>> = HashMap::new();
+    let mut clock: Option<(i64, Instant)> = None;
+    let mut replayed = 0u64;
+    let mut skipped = 0u64;
+
+    loop {
+        while remaining
+            .keys()
+            .any(|partition| queues.get(partition).is_none_or(VecDeque::is_empty))
+        {
+            let msg = consumer.recv().await?;
+            let partition = msg.partition();
+            let Some(end) = remaining.get(&partition).copied() else {
+                continue;
+            };
+            if msg.offset() >= end {
+                remaining.remove(&partition);
+                continue;
+            }
+            if msg.offset() + 1 >= end {
+                remaining.remove(&partition);
+            }
+
+            let Some(subject) = subject(msg.key(), msg.payload()) else {
+                skipped += 1;
+                continue;
+            };
+            let subject = match &config.subject_prefix {
+                Some(prefix) => format!("{prefix}.{subject}"),
+                None => subject,
+            };
+            queues.entry(partition).or_default().push_back(Pending {
+                timestamp: msg.timestamp().to_millis(),
+                subject,
+                payload: msg.payload().unwrap_or_default().to_vec(),
+            });
+        }
+
+        let Some(next) = queues
+            .values_mut()
+            .filter(|queue| !queue.is_empty())
+            .min_by_key(|queue| queue[0].timestamp)
+            .and_then(VecDeque::pop_front)
+        else {
+            break;
+        };
+
+        if config.speed > 0.0 {
+            if let Some(record_ts) = next.timestamp {
+                let (first_ts, started_at) = *clock.get_or_insert((record_ts, Instant::now()));
+                let elapsed_ms = (record_ts - first_ts).max(0) as f64 / config.speed;
+                tokio::time::sleep_until(started_at + Duration::from_millis(elapsed_ms as u64))
+                    .await;
+            }
+        }
+
+        nats_client
+            .publish(next.subject, next.payload.into())
+            .await?;
+        replayed += 1;
+    }
+
+    nats_client.flush().await?;
+    println!("Replay complete: {replayed} messages republished");
+    if skipped > 0 {
+        eprintln!("Skipped {skipped} records with no subject in their key or payload");
+    }
+
+    Ok(())
+}
+
+/// The live subject a record was published on. Records written before the
+/// bridge keyed them by subject carry a constant key instead, so theirs is
+/// rebuilt from the trade, the way the feed handler builds it.
+fn subject(key: Option<&[u8]>, payload: Option<&[u8]>) -> Option<String> {
+    let key = key.and_then(|key| std::str::from_utf8(key).ok());
+    if let Some(key) = key.filter(|key| key.starts_with("trades.")) {
+        return Some(key.to_string());
+    }
+    let trade = data::Trade::decode(payload?).ok()?;
+    let exchange = match data::trade::Exchange::try_from(trade.exchange) {
+        Ok(data::trade::Exchange::Unknown) | Err(_) => return None,
+        Ok(exchange) => exchange,
+    };
+    if trade.symbol.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "trades.{}.{}",
+        exchange.as_str_name().to_lowercase(),
+        trade.symbol.to_lowercase().replace('-', "")
+    ))
+}
+
+/// Assigns every partition of `topic` at `start` and returns, for each partition
+/// that has something to replay, the high watermark where the replay stops.
+fn assign_partitions(
+    consumer: &StreamConsumer,
+    topic: &str,
+    start: StartPosition,
+) -> Result<HashMap<i32, i64>, Box<dyn std::error::Error>> {
+    let metadata = consumer.fetch_metadata(Some(topic), METADATA_TIMEOUT)?;
+    let partitions: Vec<i32> = metadata
+        .topics()
+        .iter()
+        .filter(|t| t.name() == topic)
+        .flat_map(|t| t.partitions().iter().map(|p| p.id()))
+        .collect();
+    if partitions.is_empty() {
+        return Err(format!("Topic '{topic}' has no partitions").into());
+    }
+
+    let mut watermarks = HashMap::new();
+    let mut assignment = TopicPartitionList::new();
+    for partition in partitions {
+        let (low, high) = consumer.fetch_watermarks(topic, partition, METADATA_TIMEOUT)?;
+        watermarks.insert(partition, (low, high));
+        let offset = match start {
+            StartPosition::Beginning => Offset::Offset(low),
+            StartPosition::Offset(offset) => Offset::Offset(offset.max(low)),
+            StartPosition::Timestamp(ts) => Offset::Offset(ts),
+        };
+        assignment.add_partition_offset(topic, partition, offset)?;
+    }
+
+    if let StartPosition::Timestamp(_) = start {
+        assignment = consumer.offsets_for_times(assignment, METADATA_TIMEOUT)?;
+    }
+
+    let mut end_offsets = HashMap::new();
+    for elem in assignment.elements() {
+        let (_, high) = watermarks[&elem.partition()];
+        // Partitions with nothing at or after the start come back as `End`.
+        if let Offset::Offset(offset) = elem.offset() {
+            if offset < high {
+                end_offsets.insert(elem.partition(), high);
+            }
+        }
+    }
+
+    consumer.assign(&assignment)?;
+    Ok(end_offsets)
+}